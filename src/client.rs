@@ -1,8 +1,16 @@
-use std::{borrow::Cow, fmt::Debug, path::Path};
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+};
 
-use log::info;
+use chrono_tz::Tz;
+use log::{debug, info};
+use regex::Regex;
 use reqwest::{
     Client, Response, Url,
+    cookie::{CookieStore, Jar},
     multipart::{self, Form, Part},
 };
 use scraper::{Html, Selector};
@@ -12,19 +20,34 @@ use tokio::{fs::File, io::BufWriter, runtime::Runtime};
 use tokio_stream::StreamExt;
 use tokio_util::io::StreamReader;
 
-use super::Querypath;
+use super::{Querypath, date::DEFAULT_TIMEZONE, folder::IliasUploadResponse};
+
+pub mod background_task;
+
+static SCRIPT_TAG_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static FILE_INPUT_INIT_REGEX: OnceLock<Regex> = OnceLock::new();
+static TIMEZONE_OPTION_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
 #[derive(Debug)]
 pub struct IliasClient {
     client: Client,
+    /// Used for hosts other than ILIAS, so the session cookies stay with ILIAS
+    external_client: Client,
+    cookies: Arc<Jar>,
     runtime: Runtime,
     base_url: Url,
+    timezone: Mutex<Option<Tz>>,
 }
 
 impl IliasClient {
     pub fn new(base_url: Url) -> Result<IliasClient, Whatever> {
+        let cookies = Arc::new(Jar::default());
         let client = Client::builder()
-            .cookie_store(true)
+            .cookie_provider(cookies.clone())
+            .use_rustls_tls()
+            .build()
+            .whatever_context("Could not build reqwest client")?;
+        let external_client = Client::builder()
             .use_rustls_tls()
             .build()
             .whatever_context("Could not build reqwest client")?;
@@ -32,11 +55,80 @@ impl IliasClient {
 
         Ok(IliasClient {
             client,
+            external_client,
+            cookies,
             runtime,
             base_url,
+            timezone: Mutex::new(None),
         })
     }
 
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// The ILIAS client id (e.g. `produktiv`) of the current session
+    pub fn client_id(&self) -> Result<String, Whatever> {
+        let cookies = self
+            .cookies
+            .cookies(&self.base_url)
+            .whatever_context("No cookies set for ILIAS")?;
+        cookies
+            .to_str()
+            .whatever_context("Cookies are not valid text")?
+            .split("; ")
+            .find_map(|cookie| cookie.strip_prefix("ilClientId="))
+            .map(str::to_string)
+            .whatever_context("No client id cookie set, please authenticate first")
+    }
+
+    /// The querypath of `url` if it points to ILIAS, e.g. for absolute links found on a page
+    pub fn querypath_of(&self, url: &str) -> Option<String> {
+        let url = self.base_url.join(url).ok()?;
+        if url.origin() != self.base_url.origin() {
+            return None;
+        }
+        Some(url.get_querypath().trim_start_matches('/').to_string())
+    }
+
+    /// The timezone ILIAS renders dates in, as configured in the personal settings of the user.
+    /// It is looked up once and falls back to [`DEFAULT_TIMEZONE`] if the lookup fails.
+    pub fn timezone(&self) -> Tz {
+        let cached = *self.timezone.lock().unwrap_or_else(PoisonError::into_inner);
+        cached.unwrap_or_else(|| self.refresh_timezone())
+    }
+
+    /// Look up the timezone again, e.g. if it was first used before authenticating
+    pub fn refresh_timezone(&self) -> Tz {
+        let timezone = self.profile_timezone().unwrap_or_else(|err| {
+            debug!("Using default timezone {DEFAULT_TIMEZONE}: {err}");
+            DEFAULT_TIMEZONE
+        });
+        *self.timezone.lock().unwrap_or_else(PoisonError::into_inner) = Some(timezone);
+        timezone
+    }
+
+    fn profile_timezone(&self) -> Result<Tz, Whatever> {
+        let timezone_option_selector = TIMEZONE_OPTION_SELECTOR.get_or_init(|| {
+            Selector::parse(
+                r#"select[name="user_tz"] option[selected], select[name="time_zone"] option[selected]"#,
+            )
+            .expect("Could not parse selector")
+        });
+
+        let settings_page = self.get_querypath(
+            "ilias.php?baseClass=ildashboardgui&cmdClass=ilpersonalsettingsgui&cmd=showGeneralSettings",
+        )?;
+        let timezone = settings_page
+            .select(timezone_option_selector)
+            .next()
+            .and_then(|option| option.attr("value"))
+            .whatever_context("Did not find timezone in personal settings")?;
+        timezone
+            .parse()
+            .whatever_context(format!("Unknown timezone {timezone}"))
+    }
+
     pub fn get_querypath(&self, querypath: &str) -> Result<Html, Whatever> {
         let mut url = self.base_url.clone();
         url.set_querypath(querypath);
@@ -62,6 +154,18 @@ impl IliasClient {
         Ok(html)
     }
 
+    /// Follow all redirects of a querypath and return the final url
+    pub fn get_redirect_target(&self, querypath: &str) -> Result<Url, Whatever> {
+        let mut url = self.base_url.clone();
+        url.set_querypath(querypath);
+
+        let response = self
+            .runtime
+            .block_on(self.client.get(url.clone()).send())
+            .whatever_context(format!("No response for {url}"))?;
+        Ok(response.url().clone())
+    }
+
     pub fn post_querypath_form<T: Serialize + ?Sized + Debug>(
         &self,
         querypath: &str,
@@ -121,18 +225,62 @@ impl IliasClient {
             .whatever_context("Response had an error status code")
     }
 
+    /// Find the querypath that the ILIAS UI file input on `page` uploads its files to
+    pub fn file_input_upload_querypath(&self, page: &Html) -> Result<String, Whatever> {
+        let script_tag_selector = SCRIPT_TAG_SELECTOR.get_or_init(|| {
+            Selector::parse("body script:not([src])").expect("Could not parse selector")
+        });
+        let file_input_init_regex = FILE_INPUT_INIT_REGEX.get_or_init(|| {
+            Regex::new(r".*il\.UI\.Input\.File\.init\([^']*'[^']*',[^']*'(?<querypath>[^']+)'.*")
+                .expect("Could not parse cursed regex lol")
+        });
+
+        page.select(script_tag_selector)
+            .find_map(|script| {
+                let script = script.text().collect::<String>();
+                Some(file_input_init_regex.captures(&script)?["querypath"].to_string())
+            })
+            .whatever_context("No match for upload querypath found :(")
+    }
+
+    /// Upload a file for an ILIAS UI file input and return the id that references it in the
+    /// surrounding form
+    pub fn upload_to_file_input(
+        &self,
+        upload_querypath: &str,
+        path: &Path,
+    ) -> Result<String, Whatever> {
+        let form = Form::new().part("file[0]", self.construct_file_part(path)?);
+
+        let response = self.post_querypath_multipart(upload_querypath, form)?;
+        let response: IliasUploadResponse = self.get_json(response)?;
+        debug!("Upload response: {response:?}");
+        Ok(response.file_id)
+    }
+
     pub fn download_file(&self, querypath: &str, to: &Path) -> Result<(), Whatever> {
         let mut url = self.base_url.clone();
         url.set_querypath(querypath);
 
+        self.download_url(url, to)
+    }
+
+    /// Download from an absolute url, e.g. for resources that are not hosted by ILIAS itself
+    pub fn download_url(&self, url: Url, to: &Path) -> Result<(), Whatever> {
+        let client = if url.origin() == self.base_url.origin() {
+            &self.client
+        } else {
+            &self.external_client
+        };
         self.runtime
             .block_on(async {
-                let response = self
-                    .client
+                let response = client
                     .get(url.clone())
                     .send()
                     .await
-                    .whatever_context("Could not get response for download url")?;
+                    .whatever_context("Could not get response for download url")?
+                    .error_for_status()
+                    .whatever_context(format!("Download of {url} failed"))?;
                 let body_stream = response.bytes_stream();
                 let body_stream = body_stream.map(|result| {
                     result.map_err(std::io::Error::other)
@@ -142,6 +290,7 @@ impl IliasClient {
                 let mut options = File::options();
                 options.write(true);
                 options.create(true);
+                options.truncate(true);
                 let file = options
                     .open(to)
                     .await
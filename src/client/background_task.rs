@@ -0,0 +1,377 @@
+use std::{
+    fmt::Display,
+    path::Path,
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use log::{debug, info};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use super::IliasClient;
+use crate::querypath::Querypath;
+
+/// A background task (e.g. packaging a zip for download) as listed in the notification center
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BackgroundTask {
+    /// The id of the task ("bucket") in ILIAS
+    pub id: Option<String>,
+    pub title: String,
+    pub state: BackgroundTaskState,
+    /// The querypath of the page the task was started from
+    pub started_from: Option<String>,
+    download_querypath: Option<Querypath>,
+    cancel_querypath: Option<Querypath>,
+    remove_querypath: Option<Querypath>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackgroundTaskState {
+    /// The task is still running, with its progress in percent if ILIAS shows it
+    Running {
+        progress: Option<u8>,
+    },
+    /// The task is done and its result can be downloaded
+    Finished,
+    Failed,
+}
+
+/// How [`IliasClient::wait_for_background_task`] polls the notification center
+#[derive(Clone, Copy, Debug)]
+pub struct PollOptions {
+    /// Must not be zero
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(2),
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Any page with the main bar shows the notification center, the dashboard is a cheap one
+const NOTIFICATION_PAGE_QUERYPATH: &str = "ilias.php?baseClass=ildashboardgui&cmd=show";
+
+static NOTIFICATION_ITEM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TITLE_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TITLE_BUTTON_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static PROGRESS_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+static BUCKET_ID_REGEX: OnceLock<Regex> = OnceLock::new();
+static FROM_URL_REGEX: OnceLock<Regex> = OnceLock::new();
+static CANCEL_QUERYPATH_REGEX: OnceLock<Regex> = OnceLock::new();
+static REMOVE_QUERYPATH_REGEX: OnceLock<Regex> = OnceLock::new();
+
+impl IliasClient {
+    /// List the background tasks of the current user
+    pub fn background_tasks(&self) -> Result<Vec<BackgroundTask>, Whatever> {
+        let page = self
+            .get_querypath(NOTIFICATION_PAGE_QUERYPATH)
+            .whatever_context("Could not get notification center")?;
+        Ok(BackgroundTask::parse_all(&page))
+    }
+
+    /// Wait until a task matching `is_wanted` is no longer running and return it. Tasks in
+    /// `known_tasks`, the [`IliasClient::background_tasks`] from before starting the task, are
+    /// ignored. If the page that started the task is passed as `page`, it is checked first, as
+    /// ILIAS runs small tasks right away and shows their result in the response.
+    pub fn wait_for_background_task(
+        &self,
+        page: Option<&Html>,
+        known_tasks: &[BackgroundTask],
+        is_wanted: impl Fn(&BackgroundTask) -> bool,
+        options: &PollOptions,
+    ) -> Result<BackgroundTask, Whatever> {
+        if options.interval.is_zero() {
+            whatever!("The poll interval must not be zero")
+        }
+
+        let finished_task = |tasks: Vec<BackgroundTask>| {
+            BackgroundTask::new_tasks(tasks, known_tasks)
+                .into_iter()
+                .find(|task| {
+                    is_wanted(task) && !matches!(task.state, BackgroundTaskState::Running { .. })
+                })
+        };
+
+        if let Some(task) = page.and_then(|page| finished_task(BackgroundTask::parse_all(page))) {
+            return Ok(task);
+        }
+
+        let start = Instant::now();
+        loop {
+            if start.elapsed() >= options.timeout {
+                whatever!(
+                    "Background task did not finish within {:?}",
+                    options.timeout
+                )
+            }
+            thread::sleep(options.interval);
+
+            let tasks = self.background_tasks()?;
+            debug!("Background tasks: {tasks:?}");
+            if let Some(task) = finished_task(tasks) {
+                return Ok(task);
+            }
+        }
+    }
+
+    /// Remove all finished and failed tasks from the notification center
+    pub fn remove_finished_background_tasks(&self) -> Result<usize, Whatever> {
+        let mut removed = 0;
+        for task in self.background_tasks()? {
+            if !matches!(task.state, BackgroundTaskState::Running { .. }) {
+                task.remove(self)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+impl BackgroundTask {
+    pub fn download_querypath(&self) -> Option<&Querypath> {
+        self.download_querypath.as_ref()
+    }
+
+    pub fn cancel_querypath(&self) -> Option<&Querypath> {
+        self.cancel_querypath.as_ref()
+    }
+
+    pub fn remove_querypath(&self) -> Option<&Querypath> {
+        self.remove_querypath.as_ref()
+    }
+
+    /// Parse all background tasks shown in the notification center of `page`
+    pub fn parse_all(page: &Html) -> Vec<BackgroundTask> {
+        let notification_item_selector = NOTIFICATION_ITEM_SELECTOR.get_or_init(|| {
+            Selector::parse(".il-aggregate-notifications .il-notification-item")
+                .expect("Could not parse selector")
+        });
+
+        page.select(notification_item_selector)
+            .filter_map(|item| {
+                BackgroundTask::parse(item)
+                    .inspect_err(|err| debug!("Skipping notification item: {err}"))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn parse(item: ElementRef) -> Result<BackgroundTask, Whatever> {
+        let title_selector = TITLE_SELECTOR.get_or_init(|| {
+            Selector::parse(".media-body .il-item-notification-title")
+                .expect("Could not parse selector")
+        });
+        let title_button_selector = TITLE_BUTTON_SELECTOR.get_or_init(|| {
+            Selector::parse(".media-body .il-item-notification-title button[data-action]")
+                .expect("Could not parse selector")
+        });
+        let progress_selector = PROGRESS_SELECTOR
+            .get_or_init(|| Selector::parse("[aria-valuenow]").expect("Could not parse selector"));
+        let bucket_id_regex = BUCKET_ID_REGEX
+            .get_or_init(|| Regex::new(r"bucket_id=(?<id>\d+)").expect("Could not parse regex"));
+        let from_url_regex = FROM_URL_REGEX
+            .get_or_init(|| Regex::new("from_url=(?<url>[^&]+)&").expect("Could not parse regex"));
+        let cancel_querypath_regex = CANCEL_QUERYPATH_REGEX.get_or_init(|| {
+            Regex::new(r#"(?<querypath>ilias\.php\?[^"']*cmd=abortBucket[^"']*)"#)
+                .expect("Could not parse regex")
+        });
+        let remove_querypath_regex = REMOVE_QUERYPATH_REGEX.get_or_init(|| {
+            Regex::new(r#"(?<querypath>ilias\.php\?[^"']*cmd=removeBucket[^"']*)"#)
+                .expect("Could not parse regex")
+        });
+
+        let title = item
+            .select(title_selector)
+            .next()
+            .whatever_context("Notification item has no title")?
+            .text()
+            .collect::<String>()
+            .trim()
+            .to_string();
+
+        // Attributes are serialized with escaped ampersands
+        let item_html = item.html().replace("&amp;", "&");
+        let id = bucket_id_regex
+            .captures(&item_html)
+            .map(|captures| captures["id"].to_string());
+        let started_from = from_url_regex.captures(&item_html).and_then(|captures| {
+            let from_url = base64::prelude::BASE64_URL_SAFE_NO_PAD
+                .decode(&captures["url"])
+                .ok()?;
+            String::from_utf8(from_url).ok()
+        });
+        let cancel_querypath = cancel_querypath_regex
+            .captures(&item_html)
+            .map(|captures| Querypath::parse(&captures["querypath"]));
+        let remove_querypath = remove_querypath_regex
+            .captures(&item_html)
+            .map(|captures| Querypath::parse(&captures["querypath"]));
+
+        let download_querypath = item
+            .select(title_button_selector)
+            .next()
+            .and_then(|button| button.attr("data-action"))
+            .map(Querypath::parse);
+        let progress = item
+            .select(progress_selector)
+            .next()
+            .and_then(|progress| progress.attr("aria-valuenow"))
+            .and_then(|progress| progress.parse::<f32>().ok())
+            .map(|progress| progress.clamp(0.0, 100.0) as u8);
+
+        let lowercase_text = item.text().collect::<String>().to_lowercase();
+        let state = if download_querypath.is_some() {
+            BackgroundTaskState::Finished
+        } else if lowercase_text.contains("fehlgeschlagen") || lowercase_text.contains("failed") {
+            BackgroundTaskState::Failed
+        } else {
+            BackgroundTaskState::Running { progress }
+        };
+
+        Ok(BackgroundTask {
+            id,
+            title,
+            state,
+            started_from,
+            download_querypath,
+            cancel_querypath,
+            remove_querypath,
+        })
+    }
+
+    /// Whether the page the task was started from has the query parameter `name` set to `value`
+    pub fn was_started_with_param(&self, name: &str, value: &str) -> bool {
+        self.started_from
+            .as_deref()
+            .is_some_and(|started_from| Querypath::parse(started_from).param(name) == Some(value))
+    }
+
+    /// The tasks of `tasks` that are not in `known_tasks`. Tasks without an id can only be told
+    /// apart by their title and the page they were started from, so of several equal ones only
+    /// as many as are known are excluded.
+    pub fn new_tasks(
+        tasks: Vec<BackgroundTask>,
+        known_tasks: &[BackgroundTask],
+    ) -> Vec<BackgroundTask> {
+        let mut unmatched: Vec<&BackgroundTask> = known_tasks.iter().collect();
+        tasks
+            .into_iter()
+            .filter(
+                |task| match unmatched.iter().position(|known| task.is_same_task(known)) {
+                    Some(index) => {
+                        unmatched.remove(index);
+                        false
+                    }
+                    None => true,
+                },
+            )
+            .collect()
+    }
+
+    fn is_same_task(&self, other: &BackgroundTask) -> bool {
+        match (&self.id, &other.id) {
+            (Some(id), Some(other_id)) => id == other_id,
+            (None, None) => self.title == other.title && self.started_from == other.started_from,
+            _ => false,
+        }
+    }
+
+    /// Download the result of a finished task
+    pub fn download(&self, ilias_client: &IliasClient, to: &Path) -> Result<(), Whatever> {
+        let download_querypath = self
+            .download_querypath
+            .as_ref()
+            .whatever_context(format!("Background task {self} has nothing to download"))?;
+        info!("Downloading result of background task {self} to {to:?}");
+        ilias_client.download_file(download_querypath, to)
+    }
+
+    /// Abort a running task
+    pub fn cancel(&self, ilias_client: &IliasClient) -> Result<(), Whatever> {
+        let cancel_querypath = self
+            .cancel_querypath
+            .as_ref()
+            .whatever_context(format!("Background task {self} can not be cancelled"))?;
+        ilias_client
+            .get_querypath(cancel_querypath)
+            .whatever_context(format!("Could not cancel background task {self}"))?;
+        info!("Cancelled background task {self}");
+        Ok(())
+    }
+
+    /// Remove a finished task from the notification center
+    pub fn remove(&self, ilias_client: &IliasClient) -> Result<(), Whatever> {
+        let remove_querypath = self
+            .remove_querypath
+            .as_ref()
+            .or(self.cancel_querypath.as_ref())
+            .whatever_context(format!("Background task {self} can not be removed"))?;
+        ilias_client
+            .get_querypath(remove_querypath)
+            .whatever_context(format!("Could not remove background task {self}"))?;
+        info!("Removed background task {self}");
+        Ok(())
+    }
+}
+
+impl Display for BackgroundTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: Option<&str>, title: &str) -> BackgroundTask {
+        BackgroundTask {
+            id: id.map(str::to_string),
+            title: title.to_string(),
+            state: BackgroundTaskState::Finished,
+            started_from: Some("ilias.php?ref_id=1".to_string()),
+            download_querypath: None,
+            cancel_querypath: None,
+            remove_querypath: None,
+        }
+    }
+
+    #[test]
+    fn new_tasks_by_id() {
+        let known = [task(Some("1"), "Download")];
+        let new = BackgroundTask::new_tasks(
+            vec![task(Some("1"), "Download"), task(Some("2"), "Download")],
+            &known,
+        );
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].id.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn new_tasks_without_ids_are_counted() {
+        let known = [task(None, "Download"), task(None, "Download")];
+        let listed = vec![
+            task(None, "Download"),
+            task(None, "Download"),
+            task(None, "Download"),
+        ];
+        assert_eq!(BackgroundTask::new_tasks(listed, &known).len(), 1);
+        assert!(BackgroundTask::new_tasks(vec![task(None, "Download")], &known).is_empty());
+        assert_eq!(
+            BackgroundTask::new_tasks(vec![task(None, "Other")], &known).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::sync::OnceLock;
+
+use chrono::{
+    DateTime, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use regex::Regex;
+use snafu::Snafu;
+
+/// The timezone ILIAS renders dates in if the user profile does not specify one
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::Europe::Berlin;
+
+/// A date string that did not match any of the formats ILIAS renders
+#[derive(Debug, Snafu)]
+#[snafu(display("Could not parse date {input:?}, tried: {}", attempts.join("; ")))]
+pub struct DateParseError {
+    pub input: String,
+    /// Every format that was tried together with the reason it did not match
+    pub attempts: Vec<String>,
+}
+
+static NAMED_MONTH_DATE_REGEX: OnceLock<Regex> = OnceLock::new();
+
+const MONTHS: [&[&str]; 12] = [
+    &["Jan", "Januar", "January"],
+    &["Feb", "Februar", "February"],
+    &["Mär", "Mar", "März", "March"],
+    &["Apr", "April"],
+    &["Mai", "May"],
+    &["Jun", "Juni", "June"],
+    &["Jul", "Juli", "July"],
+    &["Aug", "August"],
+    &["Sep", "Sept", "September"],
+    &["Okt", "Oct", "Oktober", "October"],
+    &["Nov", "November"],
+    &["Dez", "Dec", "Dezember", "December"],
+];
+
+const TIME_FORMATS: [&str; 4] = ["%H:%M", "%H:%M:%S", "%I:%M %p", "%I:%M:%S %p"];
+
+/// Parse a date as rendered by ILIAS in the profile `timezone`. Ranges return their start.
+pub fn parse_date(date_string: &str, timezone: Tz) -> Result<DateTime<Local>, DateParseError> {
+    parse_date_range(date_string, timezone).map(|(start, _)| start)
+}
+
+/// Parse a date or a range like `12. Nov 2024, 10:00 - 12:00` into its start and end
+pub fn parse_date_range(
+    date_string: &str,
+    timezone: Tz,
+) -> Result<(DateTime<Local>, Option<DateTime<Local>>), DateParseError> {
+    let date_string = date_string.trim();
+    let mut attempts = vec![];
+
+    match DateTime::parse_from_rfc3339(date_string) {
+        Ok(date) => return Ok((date.with_timezone(&Local), None)),
+        Err(err) => attempts.push(format!("RFC 3339: {err}")),
+    }
+
+    match parse_single(date_string, timezone) {
+        Ok(date) => return Ok((date, None)),
+        Err(err) => attempts.push(format!("single date: {err}")),
+    }
+
+    match parse_range(date_string, timezone) {
+        Ok((start, end)) => return Ok((start, Some(end))),
+        Err(err) => attempts.push(format!("date range: {err}")),
+    }
+
+    DateParseSnafu {
+        input: date_string,
+        attempts,
+    }
+    .fail()
+}
+
+fn parse_range(
+    date_string: &str,
+    timezone: Tz,
+) -> Result<(DateTime<Local>, DateTime<Local>), String> {
+    let (start, end) = date_string
+        .split_once(" - ")
+        .or_else(|| date_string.split_once(" – "))
+        .ok_or("no range separator")?;
+    let start = parse_single(start, timezone).map_err(|err| format!("start: {err}"))?;
+
+    // The end of a range on a single day only contains the time
+    let end = match parse_time(end.trim()) {
+        Ok(time) => localize(
+            start.with_timezone(&timezone).date_naive().and_time(time),
+            timezone,
+        )?,
+        Err(_) => parse_single(end, timezone).map_err(|err| format!("end: {err}"))?,
+    };
+    Ok((start, end))
+}
+
+fn parse_single(date_string: &str, timezone: Tz) -> Result<DateTime<Local>, String> {
+    let date_string = date_string.trim();
+
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%d.%m.%Y - %H:%M",
+    ] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(date_string, format) {
+            return localize(date, timezone);
+        }
+    }
+
+    let (date, time) = match date_string.split_once(',') {
+        Some((date, time)) => (date.trim(), Some(time.trim())),
+        None => match date_string.rsplit_once(' ') {
+            // Numeric dates are separated from the time by a space only
+            Some((date, time)) if parse_time(time).is_ok() && parse_day(date, timezone).is_ok() => {
+                (date, Some(time))
+            }
+            _ => (date_string, None),
+        },
+    };
+
+    let day = parse_day(date, timezone)?;
+    let time = match time {
+        Some(time) => parse_time(time)?,
+        None => NaiveTime::MIN,
+    };
+    localize(day.and_time(time), timezone)
+}
+
+fn parse_day(date: &str, timezone: Tz) -> Result<NaiveDate, String> {
+    let named_month_date_regex = NAMED_MONTH_DATE_REGEX.get_or_init(|| {
+        Regex::new(r"^(?<day>\d{1,2})\.? (?<month>\p{L}+)\.? (?<year>\d{4})$")
+            .expect("Could not parse regex")
+    });
+
+    // Relative dates are relative to the current day in the ILIAS timezone, not the local one
+    let today = Utc::now().with_timezone(&timezone).date_naive();
+    if ["Gestern", "Yesterday"].contains(&date) {
+        return Ok(today - Days::new(1));
+    } else if ["Heute", "Today"].contains(&date) {
+        return Ok(today);
+    } else if ["Morgen", "Tomorrow"].contains(&date) {
+        return Ok(today + Days::new(1));
+    }
+
+    for format in ["%Y-%m-%d", "%d.%m.%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(date, format) {
+            return Ok(date);
+        }
+    }
+
+    let captures = named_month_date_regex
+        .captures(date)
+        .ok_or(format!("{date:?} is no known date format"))?;
+    let day: u32 = captures["day"]
+        .parse()
+        .map_err(|_| format!("invalid day {}", &captures["day"]))?;
+    let month = MONTHS
+        .iter()
+        .position(|names| names.contains(&&captures["month"]))
+        .ok_or(format!("unknown month {}", &captures["month"]))? as u32
+        + 1;
+    let year: i32 = captures["year"]
+        .parse()
+        .map_err(|_| format!("invalid year {}", &captures["year"]))?;
+    NaiveDate::from_ymd_opt(year, month, day).ok_or(format!("{date:?} does not exist"))
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    let time = time.trim();
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(time, format).ok())
+        .ok_or(format!("{time:?} is no known time format"))
+}
+
+fn localize(date: NaiveDateTime, timezone: Tz) -> Result<DateTime<Local>, String> {
+    timezone
+        .from_local_datetime(&date)
+        .earliest()
+        // Times in the gap of a switch to daylight saving time are moved forward, like PHP does
+        .or_else(|| {
+            timezone
+                .from_local_datetime(&(date + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .map(|date| date.with_timezone(&Local))
+        .ok_or(format!("{date} does not exist in {timezone}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+
+    fn berlin(date: DateTime<Local>) -> String {
+        date.with_timezone(&Berlin).to_rfc3339()
+    }
+
+    #[test]
+    fn parses_named_month_dates() {
+        let (start, end) = parse_date_range("12. Nov 2024, 10:00", Berlin).unwrap();
+        assert_eq!(berlin(start), "2024-11-12T10:00:00+01:00");
+        assert_eq!(end, None);
+
+        let start = parse_date("3. March 2025, 09:15 AM", Berlin).unwrap();
+        assert_eq!(berlin(start), "2025-03-03T09:15:00+01:00");
+    }
+
+    #[test]
+    fn parses_numeric_dates() {
+        let start = parse_date("01.06.2024 14:30", Berlin).unwrap();
+        assert_eq!(berlin(start), "2024-06-01T14:30:00+02:00");
+
+        let start = parse_date("2024-06-01 14:30:05", Berlin).unwrap();
+        assert_eq!(berlin(start), "2024-06-01T14:30:05+02:00");
+    }
+
+    #[test]
+    fn parses_ranges_on_one_day() {
+        let (start, end) = parse_date_range("12. Nov 2024, 10:00 - 12:00", Berlin).unwrap();
+        assert_eq!(berlin(start), "2024-11-12T10:00:00+01:00");
+        assert_eq!(
+            end.map(berlin).as_deref(),
+            Some("2024-11-12T12:00:00+01:00")
+        );
+    }
+
+    #[test]
+    fn parses_ranges_over_several_days() {
+        let (start, end) =
+            parse_date_range("30. Okt 2024, 10:00 - 2. Nov 2024, 12:00", Berlin).unwrap();
+        assert_eq!(berlin(start), "2024-10-30T10:00:00+01:00");
+        assert_eq!(
+            end.map(berlin).as_deref(),
+            Some("2024-11-02T12:00:00+01:00")
+        );
+    }
+
+    #[test]
+    fn moves_times_in_dst_gap_forward() {
+        let start = parse_date("30. Mär 2025, 02:30", Berlin).unwrap();
+        assert_eq!(berlin(start), "2025-03-30T03:30:00+02:00");
+    }
+
+    #[test]
+    fn takes_earliest_of_ambiguous_times() {
+        let start = parse_date("27. Okt 2024, 02:30", Berlin).unwrap();
+        assert_eq!(berlin(start), "2024-10-27T02:30:00+02:00");
+    }
+
+    #[test]
+    fn reports_all_attempts() {
+        let err = parse_date_range("sometime", Berlin).unwrap_err();
+        assert_eq!(err.input, "sometime");
+        assert_eq!(err.attempts.len(), 3);
+    }
+}
@@ -5,14 +5,22 @@ use grades::Grades;
 use log::debug;
 use regex::Regex;
 use scraper::{ElementRef, Selector, selectable::Selectable};
-use snafu::{OptionExt, ResultExt, Whatever};
+use snafu::{OptionExt, Whatever};
 
 pub mod assignment;
 pub mod grades;
 
-use super::{IliasElement, client::IliasClient, reference::Reference};
+use super::{
+    IliasElement,
+    client::IliasClient,
+    id::RefId,
+    parsed::{ParseMode, Parsed, Warnings},
+    querypath::Querypath,
+    reference::Reference,
+};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code)]
 pub struct Exercise {
     pub name: String,
@@ -34,7 +42,7 @@ impl IliasElement for Exercise {
         Some("exc")
     }
 
-    fn querypath_from_id(id: &str) -> Option<String> {
+    fn querypath_from_id(id: &RefId) -> Option<String> {
         Some(format!(
             "goto.php?target={}_{}&client_id=produktiv",
             Self::type_identifier().unwrap(),
@@ -43,6 +51,23 @@ impl IliasElement for Exercise {
     }
 
     fn parse(element: ElementRef, ilias_client: &IliasClient) -> Result<Exercise, Whatever> {
+        Self::parse_with_mode(element, ilias_client, ParseMode::Strict).map(|parsed| parsed.value)
+    }
+
+    fn parse_lenient(
+        element: ElementRef,
+        ilias_client: &IliasClient,
+    ) -> Result<Parsed<Exercise>, Whatever> {
+        Self::parse_with_mode(element, ilias_client, ParseMode::Lenient)
+    }
+}
+
+impl Exercise {
+    fn parse_with_mode(
+        element: ElementRef,
+        ilias_client: &IliasClient,
+        mode: ParseMode,
+    ) -> Result<Parsed<Exercise>, Whatever> {
         let name_selector = NAME_SELECTOR.get_or_init(|| {
             Selector::parse(".il-page-content-header").expect("Could not parse selector")
         });
@@ -92,30 +117,33 @@ impl IliasElement for Exercise {
                     .whatever_context(format!(
                         "Grades querypath {querypath} had unexpected format"
                     ))?
-                    .as_str()
-                    .to_string();
-                Some(base_querypath)
+                    .as_str();
+                Some(Querypath::parse(base_querypath))
             } else {
                 None
             };
+        let mut warnings = Warnings::new(mode);
         let mut assignments = vec![];
         for assignment in element.select(assignment_selector) {
-            let assignment = Assignment::parse(assignment, ilias_client)
-                .whatever_context("Could not parse assignment")?;
-            assignments.push(assignment);
+            if let Some(assignment) = warnings.check(
+                Assignment::parse(assignment, ilias_client),
+                assignment,
+                assignment_selector,
+                "Could not parse assignment",
+            )? {
+                assignments.push(assignment);
+            }
         }
         debug!("Assignments: {:?}", assignments);
 
-        Ok(Exercise {
+        Ok(warnings.finish(Exercise {
             name,
             description,
             assignments,
             grades: Reference::from_optional_querypath(grades_tab_querypath),
-        })
+        }))
     }
-}
 
-impl Exercise {
     pub fn get_grades(&mut self, ilias_client: &IliasClient) -> Option<&Grades> {
         let grades = &mut self.grades;
         match grades {
@@ -124,10 +152,10 @@ impl Exercise {
             Reference::Unresolved(querypath) => {
                 let ass_sub = Grades::parse(
                     ilias_client
-                        .get_querypath(querypath)
+                        .get_querypath(querypath.as_str())
                         .expect("Could not get submission page")
                         .root_element(),
-                    querypath,
+                    querypath.as_str(),
                 )
                 .expect("Could not parse submission page");
                 *grades = Reference::Resolved(ass_sub);
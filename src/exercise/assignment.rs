@@ -4,20 +4,30 @@ use chrono::{DateTime, Local};
 use log::debug;
 use regex::Regex;
 use reqwest::multipart::Form;
-use scraper::{ElementRef, Selector, selectable::Selectable};
-use snafu::{OptionExt, ResultExt, Whatever};
+use scraper::{ElementRef, Html, Selector, selectable::Selectable};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use constraints::{ConstraintViolation, SubmissionConstraints};
 
 use crate::reference::Reference;
 
+pub mod constraints;
+pub mod team;
+
 use super::super::{
     IliasElement,
     client::{AddFileWithFilename, IliasClient},
-    file::File,
+    file::{File, parse_file_size, verify_uploads},
+    form::HtmlForm,
+    id::{DeliveredFileId, FileId, RefId},
     local_file::NamedLocalFile,
     parse_date,
+    querypath::Querypath,
+    table,
 };
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code)]
 pub struct Assignment {
     pub name: String,
@@ -25,7 +35,9 @@ pub struct Assignment {
     pub submission_start_date: Option<DateTime<Local>>,
     pub submission_end_date: Option<DateTime<Local>>,
     pub attachments: Vec<File>,
+    pub constraints: SubmissionConstraints,
     submission: Reference<AssignmentSubmission>,
+    team_querypath: Option<Querypath>,
 }
 
 static PANEL_SELECTOR: OnceLock<Selector> = OnceLock::new();
@@ -36,6 +48,7 @@ static NAME_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static PROPERTY_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static ATTACHMENT_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static SUBMISSION_PAGE_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TEAM_PAGE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static INFO_PROPERTY_VALUE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static INFO_PROPERTY_KEY_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
@@ -44,7 +57,7 @@ impl IliasElement for Assignment {
         Some("ass")
     }
 
-    fn querypath_from_id(_: &str) -> Option<String> {
+    fn querypath_from_id(_: &RefId) -> Option<String> {
         None
     }
 
@@ -62,6 +75,10 @@ impl IliasElement for Assignment {
         let submission_page_selector = SUBMISSION_PAGE_SELECTOR.get_or_init(|| {
             Selector::parse("#tab_submission > a").expect("Could not parse selector")
         });
+        let team_page_selector = TEAM_PAGE_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"#tab_team > a, a[href*="cmdClass=ilexsubmissionteamgui"]"#)
+                .expect("Could not parse selector")
+        });
         let property_row_selector = PROPERTY_ROW_SELECTOR.get_or_init(|| {
             Selector::parse(".il-multi-line-cap-3").expect("Could not parse selector")
         });
@@ -82,13 +99,14 @@ impl IliasElement for Assignment {
         let submission_start_date =
             Self::get_value_for_keys(&properties, &["Startzeit", "Start Time"])
                 .ok()
-                .map(|date| parse_date(date.trim()))
-                .transpose()?;
+                .map(|date| parse_date(date.trim(), ilias_client.timezone()))
+                .transpose()
+                .whatever_context("Could not parse submission start date")?;
         let submission_end_date =
             Self::get_value_for_keys(&properties, &["Abgabetermin", "Edit Until"])
                 .or_else(|_| Self::get_value_for_keys(&properties, &["Beendet am", "Ended On"]))
-                .and_then(|date| parse_date(date.trim()))
-                .ok();
+                .ok()
+                .and_then(|date| parse_date(date.trim(), ilias_client.timezone()).ok());
         debug!("Start: {submission_start_date:?}; End: {submission_end_date:?}");
 
         let detail_querypath = element
@@ -160,9 +178,10 @@ impl IliasElement for Assignment {
                 let file = File {
                     name: filename,
                     description: "".to_string(),
-                    download_querypath: Some(download_querypath.to_string()),
+                    download_querypath: Some(Querypath::parse(download_querypath)),
                     date: None,
                     id: None,
+                    size: None,
                 };
 
                 attachments.push(file);
@@ -178,7 +197,14 @@ impl IliasElement for Assignment {
             .select(submission_page_selector)
             .next()
             .and_then(|link| link.attr("href"))
-            .map(|querypath| querypath.to_string());
+            .map(Querypath::parse);
+        let team_querypath = detail_page
+            .select(team_page_selector)
+            .next()
+            .and_then(|link| link.attr("href"))
+            .map(Querypath::parse);
+        let constraints = SubmissionConstraints::parse(detail_page.root_element());
+        debug!("Constraints: {constraints:?}");
 
         Ok(Assignment {
             name,
@@ -186,12 +212,24 @@ impl IliasElement for Assignment {
             submission_start_date,
             submission_end_date,
             attachments,
+            constraints,
             submission: Reference::from_optional_querypath(submission_page_querypath),
+            team_querypath,
         })
     }
 }
 
 impl Assignment {
+    /// The submission as far as it was fetched, see [`Assignment::get_submission`]
+    pub fn submission(&self) -> &Reference<AssignmentSubmission> {
+        &self.submission
+    }
+
+    /// The querypath of the team page, only set for team assignments
+    pub fn team_querypath(&self) -> Option<&Querypath> {
+        self.team_querypath.as_ref()
+    }
+
     pub fn is_active(&self) -> bool {
         self.submission_end_date
             .is_none_or(|date| date >= Local::now())
@@ -209,14 +247,8 @@ impl Assignment {
             Reference::Unavailable => None,
             &mut Reference::Resolved(ref submission) => Some(submission),
             Reference::Unresolved(querypath) => {
-                let ass_sub = AssignmentSubmission::parse_submissions_page(
-                    ilias_client
-                        .get_querypath(querypath)
-                        .whatever_context("Could not get submission page")?
-                        .root_element(),
-                    ilias_client,
-                )
-                .whatever_context("Could not parse submission page")?;
+                let mut ass_sub = AssignmentSubmission::load(querypath.as_str(), ilias_client)?;
+                ass_sub.constraints.merge(&self.constraints);
                 *submission = Reference::Resolved(ass_sub);
 
                 submission.try_get_resolved()
@@ -264,22 +296,154 @@ impl Assignment {
     }
 }
 
+/// What is handed in for an assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubmissionKind {
+    /// Uploaded files
+    Files,
+    /// Rich text entered in ILIAS
+    Text,
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AssignmentSubmission {
+    pub kind: SubmissionKind,
+    /// The uploaded files, empty for text submissions
     pub submissions: Vec<File>,
-    delete_querypath: String,
-    upload_querypath: String,
+    pub constraints: SubmissionConstraints,
+    querypath: Querypath,
+    delete_querypath: Option<Querypath>,
+    upload_querypath: Option<Querypath>,
+    show_text_querypath: Option<Querypath>,
+    edit_text_querypath: Option<Querypath>,
 }
 
 static UPLOAD_BUTTON_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static CONTENT_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static FILE_TABLE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static FILE_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static SOURCE_TAG_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static SHOW_TEXT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static EDIT_TEXT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TEXT_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TEXT_VALUE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
 static UPLOAD_QUERYPATH_REGEX: OnceLock<Regex> = OnceLock::new();
 
+/// Read the text of a text submission from its show or edit page
+pub(crate) fn read_submission_text(
+    ilias_client: &IliasClient,
+    querypath: &str,
+) -> Result<String, Whatever> {
+    let text_form_selector = TEXT_FORM_SELECTOR.get_or_init(|| {
+        Selector::parse(r#"#ilContentContainer form:has(textarea[name="atxt"])"#)
+            .expect("Could not parse selector")
+    });
+    let text_value_selector = TEXT_VALUE_SELECTOR.get_or_init(|| {
+        Selector::parse(
+            "#ilContentContainer form .form-control-static, #ilContentContainer form .ilFormValue",
+        )
+        .expect("Could not parse selector")
+    });
+
+    let text_page = ilias_client
+        .get_querypath(querypath)
+        .whatever_context("Could not get text submission page")?;
+    if let Some(text_form) = text_page.select(text_form_selector).next() {
+        let text_form = HtmlForm::parse(text_form)?;
+        return Ok(text_form.get("atxt").unwrap_or_default().to_string());
+    }
+    let text = text_page
+        .select(text_value_selector)
+        .next()
+        .whatever_context("Did not find submitted text")?
+        .inner_html();
+    Ok(text.trim().to_string())
+}
+
 impl AssignmentSubmission {
+    /// The querypath of the submission page
+    pub fn querypath(&self) -> &Querypath {
+        &self.querypath
+    }
+
+    pub fn delete_querypath(&self) -> Option<&Querypath> {
+        self.delete_querypath.as_ref()
+    }
+
+    pub fn upload_querypath(&self) -> Option<&Querypath> {
+        self.upload_querypath.as_ref()
+    }
+
+    /// The querypath of the page showing the submitted text, only set for text submissions
+    pub fn show_text_querypath(&self) -> Option<&Querypath> {
+        self.show_text_querypath.as_ref()
+    }
+
+    /// The querypath of the text editor, only set for text submissions that can still be edited
+    pub fn edit_text_querypath(&self) -> Option<&Querypath> {
+        self.edit_text_querypath.as_ref()
+    }
+
+    /// The submitted text as html. Empty if nothing was submitted yet.
+    pub fn text(&self, ilias_client: &IliasClient) -> Result<String, Whatever> {
+        let querypath = self
+            .edit_text_querypath
+            .as_ref()
+            .or(self.show_text_querypath.as_ref())
+            .whatever_context("This assignment has no text submission")?;
+        read_submission_text(ilias_client, querypath)
+    }
+
+    /// Replace the submitted text with `text`, which may contain html
+    pub fn edit_text(&self, ilias_client: &IliasClient, text: &str) -> Result<(), Whatever> {
+        let text_form_selector = TEXT_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"#ilContentContainer form:has(textarea[name="atxt"])"#)
+                .expect("Could not parse selector")
+        });
+
+        let Some(edit_text_querypath) = &self.edit_text_querypath else {
+            whatever!("The text of this submission can not be edited")
+        };
+        let edit_page = ilias_client
+            .get_querypath(edit_text_querypath)
+            .whatever_context("Could not get text editor")?;
+        let mut text_form = HtmlForm::parse(
+            edit_page
+                .select(text_form_selector)
+                .next()
+                .whatever_context("Did not find text editor form")?,
+        )?;
+        text_form.set("atxt", text);
+
+        let response = text_form
+            .submit(ilias_client, "updateAssignmentText")
+            .whatever_context("Could not post text submission")?;
+        if ilias_client
+            .is_alert_response(response)
+            .whatever_context("Could not check error state of response")?
+        {
+            whatever!("ILIAS rejected the text submission")
+        }
+        Ok(())
+    }
+
+    fn load(querypath: &str, ilias_client: &IliasClient) -> Result<AssignmentSubmission, Whatever> {
+        AssignmentSubmission::parse_submissions_page(
+            querypath,
+            ilias_client
+                .get_querypath(querypath)
+                .whatever_context("Could not get submission page")?
+                .root_element(),
+            ilias_client,
+        )
+        .whatever_context("Could not parse submission page")
+    }
+
     fn parse_submissions_page(
+        querypath: &str,
         submission_page: ElementRef,
         ilias_client: &IliasClient,
     ) -> Result<AssignmentSubmission, Whatever> {
@@ -292,6 +456,9 @@ impl AssignmentSubmission {
         let source_tag_selector = SOURCE_TAG_SELECTOR.get_or_init(|| {
             Selector::parse("body > script:not([src])").expect("Could not parse selector")
         });
+        let file_table_selector = FILE_TABLE_SELECTOR.get_or_init(|| {
+            Selector::parse("#ilContentContainer form table[id]").expect("Could not parse selector")
+        });
         let file_row_selector = FILE_ROW_SELECTOR.get_or_init(|| {
             Selector::parse("#ilContentContainer form tbody tr").expect("Could not parse selector")
         });
@@ -299,8 +466,63 @@ impl AssignmentSubmission {
             Regex::new(r#"'(?P<querypath>ilias\.php\?[a-zA-Z=&0-9:_]+cmd=upload[a-zA-Z=&0-9:_]+)'"#)
                 .expect("Could not parse regex")
         });
+        let show_text_selector = SHOW_TEXT_SELECTOR.get_or_init(|| {
+            Selector::parse(
+                r#"[href*="cmd=showAssignmentText"], [data-action*="cmd=showAssignmentText"]"#,
+            )
+            .expect("Could not parse selector")
+        });
+        let edit_text_selector = EDIT_TEXT_SELECTOR.get_or_init(|| {
+            Selector::parse(
+                r#"[href*="cmd=editAssignmentText"], [data-action*="cmd=editAssignmentText"]"#,
+            )
+            .expect("Could not parse selector")
+        });
+
+        if team::requires_team(submission_page) {
+            whatever!("This is a team assignment, create or join a team before submitting")
+        }
+
+        let action_querypath = |selector| {
+            submission_page
+                .select(selector)
+                .next()
+                .and_then(|action| action.attr("href").or(action.attr("data-action")))
+                .map(Querypath::parse)
+        };
+        let show_text_querypath = action_querypath(show_text_selector);
+        let edit_text_querypath = action_querypath(edit_text_selector);
+        if show_text_querypath.is_some() || edit_text_querypath.is_some() {
+            debug!("Text submission: {show_text_querypath:?}, {edit_text_querypath:?}");
+            return Ok(AssignmentSubmission {
+                kind: SubmissionKind::Text,
+                submissions: vec![],
+                constraints: SubmissionConstraints::default(),
+                querypath: Querypath::parse(querypath),
+                delete_querypath: None,
+                upload_querypath: None,
+                show_text_querypath,
+                edit_text_querypath,
+            });
+        }
+
+        // The delivered files table is paginated if there are many files
+        let file_table_id = submission_page
+            .select(file_table_selector)
+            .next()
+            .and_then(|table| table.attr("id"));
+        let all_pages = match file_table_id {
+            Some(table_id) => table::all_pages(ilias_client, submission_page, table_id)?,
+            None => None,
+        };
+        let pages = match &all_pages {
+            Some(pages) => pages.iter().map(Html::root_element).collect(),
+            None => vec![submission_page],
+        };
 
-        let file_rows = submission_page.select(file_row_selector);
+        let file_rows = pages
+            .into_iter()
+            .flat_map(|page| page.select(file_row_selector));
         let mut uploaded_files = vec![];
         for row in file_rows.filter(|&row| row.child_elements().count() > 1) {
             let mut children = row.child_elements();
@@ -318,21 +540,21 @@ impl AssignmentSubmission {
                 .whatever_context("Did not find second column")?
                 .text()
                 .collect();
-            let submission_date = loop {
-                let parsed_date = parse_date(
-                    &children
-                        .next()
-                        .whatever_context("Did not find date column")?
-                        .text()
-                        .collect::<String>(),
-                );
-                match parsed_date {
-                    Ok(date) => break date,
-                    _ => continue,
+            // The date and, depending on the ILIAS version, the size are followed by the actions
+            let mut submission_date = None;
+            let mut size = None;
+            let mut last_column = None;
+            for column in children {
+                let text = column.text().collect::<String>();
+                if submission_date.is_none() {
+                    submission_date = parse_date(&text, ilias_client.timezone()).ok();
+                } else if size.is_none() {
+                    size = parse_file_size(&text);
                 }
-            };
-            let download_querypath = children
-                .last()
+                last_column = Some(column);
+            }
+            let submission_date = submission_date.whatever_context("Did not find date column")?;
+            let download_querypath = last_column
                 .whatever_context("Did not find last column")?
                 .child_elements()
                 .next()
@@ -341,11 +563,12 @@ impl AssignmentSubmission {
                 .whatever_context("Did not find href attribute")?;
 
             let file = File {
-                id: Some(id.to_string()),
+                id: Some(FileId::Delivered(DeliveredFileId::new(id))),
                 name: file_name,
                 description: String::new(),
                 date: Some(submission_date),
-                download_querypath: Some(download_querypath.to_string()),
+                download_querypath: Some(Querypath::parse(download_querypath)),
+                size,
             };
 
             uploaded_files.push(file);
@@ -357,8 +580,7 @@ impl AssignmentSubmission {
             .whatever_context("Did not find deletion form")?
             .value()
             .attr("action")
-            .whatever_context("Did not find action attribute for delete querypath")?
-            .to_string();
+            .whatever_context("Did not find action attribute for delete querypath")?;
 
         let upload_form_querypath = submission_page
             .select(upload_button_selector)
@@ -374,16 +596,25 @@ impl AssignmentSubmission {
             .whatever_context("Missing script with upload path")?
             .text()
             .collect::<String>();
-        let upload_querypath = upload_querypath_regex
+        let upload_querypath = &upload_querypath_regex
             .captures(&script)
-            .whatever_context("Could not find upload querypath")?["querypath"]
-            .to_string();
+            .whatever_context("Could not find upload querypath")?["querypath"];
         debug!("Upload querypath: {}", upload_querypath);
 
+        // The upload form explains the constraints more precisely than the submission page
+        let mut constraints = SubmissionConstraints::parse(upload_page.root_element());
+        constraints.merge(&SubmissionConstraints::parse(submission_page));
+        debug!("Constraints: {constraints:?}");
+
         Ok(AssignmentSubmission {
+            kind: SubmissionKind::Files,
             submissions: uploaded_files,
-            delete_querypath,
-            upload_querypath,
+            constraints,
+            querypath: Querypath::parse(querypath),
+            delete_querypath: Some(Querypath::parse(delete_querypath)),
+            upload_querypath: Some(Querypath::parse(upload_querypath)),
+            show_text_querypath: None,
+            edit_text_querypath: None,
         })
     }
 
@@ -394,22 +625,56 @@ impl AssignmentSubmission {
     ) -> Result<(), Whatever> {
         let mut form_args = files
             .iter()
-            .map(|&file| file.id.clone().expect("Files to delete must have an id"))
-            .map(|id| ("delivered[]", id))
-            .collect::<Vec<_>>();
-        form_args.push(("cmd[deleteDelivered]", String::from("Löschen")));
+            .map(|&file| {
+                file.id
+                    .as_ref()
+                    .and_then(FileId::delivered_id)
+                    .whatever_context(format!("File {} was not delivered", file.name))
+                    .map(|id| ("delivered[]", id.as_str()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        form_args.push(("cmd[deleteDelivered]", "Löschen"));
 
+        let delete_querypath = self
+            .delete_querypath
+            .as_ref()
+            .whatever_context("Text submissions have no files to delete")?;
         ilias_client
-            .post_querypath_form(&self.delete_querypath, &form_args)
+            .post_querypath_form(delete_querypath, &form_args)
             .whatever_context("Could not post assignment deletion form")?;
         Ok(())
     }
 
+    /// Check `files` against the constraints of the assignment, counting submitted files as well
+    pub fn check_files(&self, files: &[NamedLocalFile]) -> Vec<ConstraintViolation> {
+        self.constraints.check(files, self.submissions.len())
+    }
+
+    /// Upload `files` if they meet the constraints of the assignment and return them as listed
+    /// afterwards
     pub fn upload_files(
         &self,
         ilias_client: &IliasClient,
         files: &[NamedLocalFile],
-    ) -> Result<(), Whatever> {
+    ) -> Result<Vec<File>, Whatever> {
+        // `self` may be outdated if files were submitted since loading it
+        let current = AssignmentSubmission::load(&self.querypath, ilias_client)?;
+        let violations = current.check_files(files);
+        if !violations.is_empty() {
+            whatever!(
+                "Files do not meet the submission constraints: {}",
+                violations
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )
+        }
+
+        let upload_querypath = self
+            .upload_querypath
+            .as_ref()
+            .whatever_context("Files can not be uploaded to text submissions")?;
         let mut form = Form::new();
 
         for (index, file_data) in files.iter().enumerate() {
@@ -423,12 +688,22 @@ impl AssignmentSubmission {
                 .text("ilfilehash", "aaaa");
         }
         debug!("Form: {:?}", form);
-        debug!("Upload querypath: {}", self.upload_querypath);
+        debug!("Upload querypath: {}", upload_querypath);
 
+        let known_ids: Vec<FileId> = current
+            .submissions
+            .into_iter()
+            .filter_map(|file| file.id)
+            .collect();
         ilias_client
-            .post_querypath_multipart(&self.upload_querypath, form)
+            .post_querypath_multipart(upload_querypath, form)
             .whatever_context("Could not post assignment upload form")?;
-        Ok(())
-        // TODO: Maybe push files to submission here
+
+        let listed = AssignmentSubmission::load(&self.querypath, ilias_client)?
+            .submissions
+            .into_iter()
+            .filter(|file| file.id.as_ref().is_none_or(|id| !known_ids.contains(id)))
+            .collect();
+        verify_uploads(files, listed).whatever_context("Could not verify submission upload")
     }
 }
@@ -0,0 +1,294 @@
+use std::{fmt::Display, fs, sync::OnceLock};
+
+use regex::Regex;
+use scraper::ElementRef;
+
+use crate::{file::parse_file_size, local_file::NamedLocalFile};
+
+/// Restrictions ILIAS places on the files submitted for an assignment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubmissionConstraints {
+    pub max_files: Option<u32>,
+    /// Lowercase file suffixes without leading dot
+    pub allowed_suffixes: Option<Vec<String>>,
+    /// In bytes
+    pub max_file_size: Option<u64>,
+}
+
+/// A way in which files break the [`SubmissionConstraints`] of an assignment
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConstraintViolation {
+    TooManyFiles {
+        max_files: u32,
+        files: usize,
+    },
+    SuffixNotAllowed {
+        name: String,
+    },
+    FileTooLarge {
+        name: String,
+        size: u64,
+        max_file_size: u64,
+    },
+    Unreadable {
+        name: String,
+        reason: String,
+    },
+}
+
+static MAX_FILES_REGEX: OnceLock<Regex> = OnceLock::new();
+static ALLOWED_SUFFIXES_REGEX: OnceLock<Regex> = OnceLock::new();
+static MAX_FILE_SIZE_REGEX: OnceLock<Regex> = OnceLock::new();
+
+impl SubmissionConstraints {
+    /// Read the constraints from the texts ILIAS shows on assignment and upload pages
+    pub fn parse(element: ElementRef) -> SubmissionConstraints {
+        let max_files_regex = MAX_FILES_REGEX.get_or_init(|| {
+            Regex::new(
+                r"(?i)(maximale anzahl (an |von )?dateien|max(imum|\.)? (number of )?files)\D{0,10}(?<max>\d+)",
+            )
+            .expect("Could not parse regex")
+        });
+        let allowed_suffixes_regex = ALLOWED_SUFFIXES_REGEX.get_or_init(|| {
+            Regex::new(
+                r"(?i)(erlaubte dateiendungen|zulässige dateitypen|erlaubte dateitypen|allowed file (types|suffixes|extensions))\s*:?\s*(?<suffixes>\.?[a-z0-9]+(\s*[,;]\s*\.?[a-z0-9]+)*)",
+            )
+            .expect("Could not parse regex")
+        });
+        let max_file_size_regex = MAX_FILE_SIZE_REGEX.get_or_init(|| {
+            Regex::new(
+                r"(?i)(maximale dateigröße|max(imum|\.)? (upload |file )?size)\s*:?\s*(?<size>\d+([.,]\d+)?)\s*(?<unit>[kmg]i?b|bytes?)",
+            )
+            .expect("Could not parse regex")
+        });
+
+        let text = element.text().collect::<Vec<_>>().join(" ");
+
+        let max_files = max_files_regex
+            .captures(&text)
+            .and_then(|captures| captures["max"].parse().ok());
+        let allowed_suffixes = allowed_suffixes_regex.captures(&text).map(|captures| {
+            captures["suffixes"]
+                .split([',', ';'])
+                .map(|suffix| suffix.trim().trim_start_matches('.').to_lowercase())
+                .filter(|suffix| !suffix.is_empty())
+                .collect()
+        });
+        let max_file_size = max_file_size_regex.captures(&text).and_then(|captures| {
+            parse_file_size(&format!("{} {}", &captures["size"], &captures["unit"]))
+        });
+
+        SubmissionConstraints {
+            max_files,
+            allowed_suffixes,
+            max_file_size,
+        }
+    }
+
+    /// Fill in the constraints missing here from `other`
+    pub fn merge(&mut self, other: &SubmissionConstraints) {
+        self.max_files = self.max_files.or(other.max_files);
+        if self.allowed_suffixes.is_none() {
+            self.allowed_suffixes = other.allowed_suffixes.clone();
+        }
+        self.max_file_size = self.max_file_size.or(other.max_file_size);
+    }
+
+    pub fn requires_single_file(&self) -> bool {
+        self.max_files == Some(1)
+    }
+
+    /// Check `files` against the constraints, counting `already_submitted` files as well
+    pub fn check(
+        &self,
+        files: &[NamedLocalFile],
+        already_submitted: usize,
+    ) -> Vec<ConstraintViolation> {
+        let mut violations = vec![];
+
+        if let Some(max_files) = self.max_files
+            && files.len() + already_submitted > max_files as usize
+        {
+            violations.push(ConstraintViolation::TooManyFiles {
+                max_files,
+                files: files.len() + already_submitted,
+            });
+        }
+
+        for file in files {
+            if let Some(allowed_suffixes) = &self.allowed_suffixes {
+                let suffix = file
+                    .name
+                    .rsplit_once('.')
+                    .map(|(_, suffix)| suffix.to_lowercase());
+                if !suffix.is_some_and(|suffix| allowed_suffixes.contains(&suffix)) {
+                    violations.push(ConstraintViolation::SuffixNotAllowed {
+                        name: file.name.clone(),
+                    });
+                }
+            }
+
+            if let Some(max_file_size) = self.max_file_size {
+                match fs::metadata(&file.path) {
+                    Ok(metadata) if metadata.len() > max_file_size => {
+                        violations.push(ConstraintViolation::FileTooLarge {
+                            name: file.name.clone(),
+                            size: metadata.len(),
+                            max_file_size,
+                        })
+                    }
+                    Ok(_) => {}
+                    Err(err) => violations.push(ConstraintViolation::Unreadable {
+                        name: file.name.clone(),
+                        reason: err.to_string(),
+                    }),
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+impl Display for ConstraintViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstraintViolation::TooManyFiles { max_files, files } => {
+                write!(
+                    f,
+                    "{files} files submitted, but at most {max_files} are allowed"
+                )
+            }
+            ConstraintViolation::SuffixNotAllowed { name } => {
+                write!(f, "The file type of {name} is not allowed")
+            }
+            ConstraintViolation::FileTooLarge {
+                name,
+                size,
+                max_file_size,
+            } => write!(
+                f,
+                "{name} has {size} bytes, but at most {max_file_size} are allowed"
+            ),
+            ConstraintViolation::Unreadable { name, reason } => {
+                write!(f, "Could not read {name}: {reason}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use scraper::Html;
+
+    use super::*;
+    use crate::local_file::TestFiles;
+
+    fn parse(html: &str) -> SubmissionConstraints {
+        SubmissionConstraints::parse(Html::parse_fragment(html).root_element())
+    }
+
+    #[test]
+    fn parses_german_constraints() {
+        let constraints = parse(
+            "<div>Maximale Anzahl an Dateien: 2</div>\
+             <div>Erlaubte Dateiendungen: .pdf, .ZIP</div>\
+             <div>Maximale Dateigröße: 1,5 MB</div>",
+        );
+        assert_eq!(
+            constraints,
+            SubmissionConstraints {
+                max_files: Some(2),
+                allowed_suffixes: Some(vec!["pdf".to_string(), "zip".to_string()]),
+                max_file_size: Some(1572864),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_english_constraints() {
+        let constraints = parse("<p>Maximum number of files: 1</p><p>Max. upload size 10 KB</p>");
+        assert!(constraints.requires_single_file());
+        assert_eq!(constraints.allowed_suffixes, None);
+        assert_eq!(constraints.max_file_size, Some(10240));
+    }
+
+    #[test]
+    fn merge_keeps_own_constraints() {
+        let mut constraints = SubmissionConstraints {
+            max_files: Some(1),
+            ..Default::default()
+        };
+        constraints.merge(&SubmissionConstraints {
+            max_files: Some(3),
+            allowed_suffixes: Some(vec!["pdf".to_string()]),
+            max_file_size: None,
+        });
+        assert_eq!(constraints.max_files, Some(1));
+        assert_eq!(constraints.allowed_suffixes, Some(vec!["pdf".to_string()]));
+    }
+
+    #[test]
+    fn reports_violations() {
+        let constraints = SubmissionConstraints {
+            max_files: Some(2),
+            allowed_suffixes: Some(vec!["pdf".to_string()]),
+            max_file_size: Some(4),
+        };
+        let test_files = TestFiles::new();
+        let files = [
+            test_files.file("small.PDF", b"1234"),
+            test_files.file("large.pdf", b"12345"),
+            test_files.file("notes.txt", b""),
+        ];
+        assert_eq!(
+            constraints.check(&files, 0),
+            vec![
+                ConstraintViolation::TooManyFiles {
+                    max_files: 2,
+                    files: 3,
+                },
+                ConstraintViolation::FileTooLarge {
+                    name: "large.pdf".to_string(),
+                    size: 5,
+                    max_file_size: 4,
+                },
+                ConstraintViolation::SuffixNotAllowed {
+                    name: "notes.txt".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_already_submitted_files() {
+        let constraints = SubmissionConstraints {
+            max_files: Some(1),
+            ..Default::default()
+        };
+        let test_files = TestFiles::new();
+        let files = [test_files.file("solution.pdf", b"")];
+        assert!(constraints.check(&files, 0).is_empty());
+        assert_eq!(constraints.check(&files, 1).len(), 1);
+    }
+
+    #[test]
+    fn reports_unreadable_files() {
+        let constraints = SubmissionConstraints {
+            max_file_size: Some(1),
+            ..Default::default()
+        };
+        let files = [NamedLocalFile {
+            name: "missing.pdf".to_string(),
+            path: PathBuf::from("/nonexistent/missing.pdf"),
+        }];
+        assert!(matches!(
+            constraints.check(&files, 0).as_slice(),
+            [ConstraintViolation::Unreadable { name, .. }] if name == "missing.pdf"
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+use std::sync::OnceLock;
+
+use log::{debug, info};
+use scraper::{ElementRef, Html, Selector};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use super::Assignment;
+use crate::{client::IliasClient, form::HtmlForm, id::MemberId, table::TableRow};
+
+/// The team of the current user for a team assignment
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Team {
+    pub members: Vec<TeamMember>,
+    /// Whether the team can still be changed, which ILIAS forbids after the deadline
+    pub editable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TeamMember {
+    /// Only known while the team can be changed
+    pub id: Option<MemberId>,
+    pub name: String,
+    pub login: Option<String>,
+}
+
+static CREATE_TEAM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static ADD_MEMBER_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static MEMBER_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static MEMBER_HEADER_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static MEMBER_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static MEMBER_CHECKBOX_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static REMOVE_CONFIRMATION_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+/// Whether `page` asks to create or join a team before anything can be submitted
+pub(super) fn requires_team(page: ElementRef) -> bool {
+    page.select(create_team_selector()).next().is_some()
+}
+
+fn create_team_selector() -> &'static Selector {
+    CREATE_TEAM_SELECTOR.get_or_init(|| {
+        Selector::parse(
+            r#"[href*="cmd=createTeam"], [data-action*="cmd=createTeam"], [name="cmd[createTeam]"]"#,
+        )
+        .expect("Could not parse selector")
+    })
+}
+
+/// Add the user with `login` to the team managed on the team page at `team_querypath`
+pub(crate) fn add_team_member(
+    ilias_client: &IliasClient,
+    team_querypath: &str,
+    login: &str,
+) -> Result<(), Whatever> {
+    let add_member_form_selector = ADD_MEMBER_FORM_SELECTOR.get_or_init(|| {
+        Selector::parse(r#"form:has(input[name="user_login"])"#).expect("Could not parse selector")
+    });
+
+    let team_page = ilias_client
+        .get_querypath(team_querypath)
+        .whatever_context("Could not get team page")?;
+    if requires_team(team_page.root_element()) {
+        whatever!("Create a team before adding members")
+    }
+    let mut add_member_form = HtmlForm::parse(
+        team_page
+            .select(add_member_form_selector)
+            .next()
+            .whatever_context("ILIAS does not allow adding members to this team")?,
+    )?;
+    add_member_form.set("user_login", login);
+
+    let response = add_member_form
+        .submit(ilias_client, "addTeamMemberAction")
+        .whatever_context("Could not post new team member")?;
+    if ilias_client
+        .is_alert_response(response)
+        .whatever_context("Could not check error state of response")?
+    {
+        whatever!("ILIAS refused to add {login} to the team")
+    }
+    Ok(())
+}
+
+/// Remove the members with `ids` from the team managed on the team page at `team_querypath`
+pub(crate) fn remove_team_members(
+    ilias_client: &IliasClient,
+    team_querypath: &str,
+    ids: &[&MemberId],
+) -> Result<(), Whatever> {
+    let member_form_selector = MEMBER_FORM_SELECTOR.get_or_init(|| {
+        Selector::parse(r#"form:has(input[name="id[]"])"#).expect("Could not parse selector")
+    });
+    let remove_confirmation_form_selector = REMOVE_CONFIRMATION_FORM_SELECTOR.get_or_init(|| {
+        Selector::parse(r#"form:has([name="cmd[deleteTeamMember]"])"#)
+            .expect("Could not parse selector")
+    });
+
+    let team_page = ilias_client
+        .get_querypath(team_querypath)
+        .whatever_context("Could not get team page")?;
+    let mut member_form = HtmlForm::parse(
+        team_page
+            .select(member_form_selector)
+            .next()
+            .whatever_context("ILIAS does not allow removing members from this team")?,
+    )?;
+    member_form.remove("id[]");
+    for id in ids {
+        member_form.add("id[]", id.as_str());
+    }
+
+    let response = member_form
+        .submit(ilias_client, "confirmDeleteTeamMember")
+        .whatever_context("Could not request removal of team members")?;
+    let confirmation_page = Html::parse_document(&ilias_client.get_text(response)?);
+    let confirmation_form = HtmlForm::parse(
+        confirmation_page
+            .select(remove_confirmation_form_selector)
+            .next()
+            .whatever_context("Did not find confirmation for removing team members")?,
+    )?;
+    confirmation_form
+        .submit(ilias_client, "deleteTeamMember")
+        .whatever_context("Could not remove team members")?;
+    Ok(())
+}
+
+impl Assignment {
+    pub fn is_team_assignment(&self) -> bool {
+        self.team_querypath.is_some()
+    }
+
+    /// The team of the current user, `None` if they are not in a team yet
+    pub fn team(&self, ilias_client: &IliasClient) -> Result<Option<Team>, Whatever> {
+        let team_page = self.team_page(ilias_client)?;
+        if requires_team(team_page.root_element()) {
+            return Ok(None);
+        }
+        Team::parse(team_page.root_element()).map(Some)
+    }
+
+    /// Create a new team containing only the current user
+    pub fn create_team(&self, ilias_client: &IliasClient) -> Result<Team, Whatever> {
+        self.ensure_team_editable()?;
+
+        let team_page = self.team_page(ilias_client)?;
+        let create_team_button = team_page
+            .select(create_team_selector())
+            .next()
+            .whatever_context(format!("You already are in a team for {}", self.name))?;
+        if let Some(querypath) = create_team_button
+            .attr("href")
+            .or(create_team_button.attr("data-action"))
+        {
+            ilias_client
+                .get_querypath(querypath)
+                .whatever_context("Could not create team")?;
+        } else {
+            // The button is part of a form
+            let form = create_team_button
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|ancestor| ancestor.value().name() == "form")
+                .whatever_context("Create team button is not part of a form")?;
+            HtmlForm::parse(form)?
+                .submit(ilias_client, "createTeam")
+                .whatever_context("Could not create team")?;
+        }
+        info!("Created team for {}", self.name);
+
+        self.team(ilias_client)?
+            .whatever_context("ILIAS did not create the team")
+    }
+
+    /// Add the users with the given logins to the team of the current user
+    pub fn add_team_members(
+        &self,
+        ilias_client: &IliasClient,
+        logins: &[&str],
+    ) -> Result<Team, Whatever> {
+        self.ensure_team_editable()?;
+        let team_querypath = self.required_team_querypath()?;
+
+        for login in logins {
+            add_team_member(ilias_client, team_querypath, login).whatever_context(format!(
+                "Could not add {login} to the team for {}",
+                self.name
+            ))?;
+            info!("Added {login} to the team for {}", self.name);
+        }
+
+        self.team(ilias_client)?
+            .whatever_context("Team vanished while adding members")
+    }
+
+    /// Remove `members` from the team of the current user
+    pub fn remove_team_members(
+        &self,
+        ilias_client: &IliasClient,
+        members: &[&TeamMember],
+    ) -> Result<Option<Team>, Whatever> {
+        self.ensure_team_editable()?;
+
+        let ids = members
+            .iter()
+            .map(|member| {
+                member
+                    .id
+                    .as_ref()
+                    .whatever_context(format!("{} can not be removed from the team", member.name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        remove_team_members(ilias_client, self.required_team_querypath()?, &ids).whatever_context(
+            format!("Could not remove members from the team for {}", self.name),
+        )?;
+        info!(
+            "Removed {} members from the team for {}",
+            members.len(),
+            self.name
+        );
+
+        self.team(ilias_client)
+    }
+
+    /// Leave the team of the current user, whose login is `own_login`
+    pub fn leave_team(&self, ilias_client: &IliasClient, own_login: &str) -> Result<(), Whatever> {
+        let team = self
+            .team(ilias_client)?
+            .whatever_context(format!("You are not in a team for {}", self.name))?;
+        let own_member = team
+            .members
+            .iter()
+            .find(|member| member.login.as_deref() == Some(own_login))
+            .whatever_context(format!("{own_login} is not a member of the team"))?;
+        self.remove_team_members(ilias_client, &[own_member])?;
+        Ok(())
+    }
+
+    fn required_team_querypath(&self) -> Result<&str, Whatever> {
+        self.team_querypath
+            .as_deref()
+            .whatever_context(format!("{} is not a team assignment", self.name))
+    }
+
+    fn team_page(&self, ilias_client: &IliasClient) -> Result<Html, Whatever> {
+        ilias_client
+            .get_querypath(self.required_team_querypath()?)
+            .whatever_context("Could not get team page")
+    }
+
+    fn ensure_team_editable(&self) -> Result<(), Whatever> {
+        if !self.is_active() {
+            whatever!(
+                "Teams for {} can not be changed outside of the submission period",
+                self.name
+            )
+        }
+        Ok(())
+    }
+}
+
+impl Team {
+    fn parse(team_page: ElementRef) -> Result<Team, Whatever> {
+        let member_header_selector = MEMBER_HEADER_SELECTOR.get_or_init(|| {
+            Selector::parse("#ilContentContainer table thead th").expect("Could not parse selector")
+        });
+        let member_row_selector = MEMBER_ROW_SELECTOR.get_or_init(|| {
+            Selector::parse("#ilContentContainer table tbody tr:has(td)")
+                .expect("Could not parse selector")
+        });
+        let member_checkbox_selector = MEMBER_CHECKBOX_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"input[name="id[]"]"#).expect("Could not parse selector")
+        });
+
+        let columns = TableRow::columns(team_page, member_header_selector);
+        debug!("Team columns: {columns:?}");
+
+        let mut members = vec![];
+        let mut editable = false;
+        for row in team_page.select(member_row_selector) {
+            // Without checkboxes the team can not be changed anymore
+            let id = row
+                .select(member_checkbox_selector)
+                .next()
+                .and_then(|checkbox| checkbox.attr("value"))
+                .map(MemberId::new);
+            editable |= id.is_some();
+
+            let table_row = TableRow::parse(row, &columns);
+            let name = table_row
+                .value(&["Name", "Nachname, Vorname", "Last Name, First Name"])
+                .whatever_context("Could not find name of team member")?
+                .to_string();
+            let login = table_row
+                .value(&["Benutzername", "Login", "Anmeldename"])
+                .cloned();
+            members.push(TeamMember { id, name, login });
+        }
+        debug!("Team members: {members:?}");
+
+        Ok(Team { members, editable })
+    }
+}
@@ -1,16 +1,29 @@
 use std::{path::Path, sync::OnceLock};
 
-use base64::Engine;
-use regex::Regex;
 use scraper::{ElementRef, Html, Selector, selectable::Selectable};
-use snafu::{OptionExt, ResultExt, Whatever};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
 use submission::GradeSubmission;
-
-use crate::{IliasElement, client::IliasClient, reference::Reference};
+use team::ExerciseMember;
+
+use crate::{
+    IliasElement,
+    client::{
+        IliasClient,
+        background_task::{BackgroundTaskState, PollOptions},
+    },
+    form::HtmlForm,
+    id::{AssignmentId, RefId},
+    parsed::{ParseMode, Parsed, Warnings},
+    querypath::Querypath,
+    reference::Reference,
+    table,
+};
 
 pub mod submission;
+pub mod team;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Grades {
     pub assignment_grades: Vec<Reference<GradePage>>,
 }
@@ -29,7 +42,7 @@ impl Grades {
                 let ass_id = option.attr("value").expect("Option did not have a value");
 
                 let querypath = format!("{base_querypath}&ass_id={ass_id}");
-                Reference::Unresolved(querypath)
+                Reference::Unresolved(Querypath::from(querypath))
             })
             .collect::<Vec<_>>();
 
@@ -40,11 +53,14 @@ impl Grades {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GradePage {
     pub name: String,
-    ass_id: String,
-    toolbar_form_querypath: String,
+    ass_id: AssignmentId,
+    toolbar_form_querypath: Querypath,
+    toolbar_form: HtmlForm,
     pub submissions: Vec<GradeSubmission>,
+    members: Vec<ExerciseMember>,
 }
 
 static SELECTED_ASSIGNMENT_DROPDOWN_SELECTOR: OnceLock<Selector> = OnceLock::new();
@@ -56,11 +72,28 @@ impl IliasElement for GradePage {
         None
     }
 
-    fn querypath_from_id(_id: &str) -> Option<String> {
+    fn querypath_from_id(_id: &RefId) -> Option<String> {
         None
     }
 
-    fn parse(element: ElementRef, _ilias_client: &IliasClient) -> Result<Self, Whatever> {
+    fn parse(element: ElementRef, ilias_client: &IliasClient) -> Result<Self, Whatever> {
+        Self::parse_with_mode(element, ilias_client, ParseMode::Strict).map(|parsed| parsed.value)
+    }
+
+    fn parse_lenient(
+        element: ElementRef,
+        ilias_client: &IliasClient,
+    ) -> Result<Parsed<Self>, Whatever> {
+        Self::parse_with_mode(element, ilias_client, ParseMode::Lenient)
+    }
+}
+
+impl GradePage {
+    fn parse_with_mode(
+        element: ElementRef,
+        ilias_client: &IliasClient,
+        mode: ParseMode,
+    ) -> Result<Parsed<GradePage>, Whatever> {
         let selected_assignment_dropdown_selector = SELECTED_ASSIGNMENT_DROPDOWN_SELECTOR
             .get_or_init(|| {
                 Selector::parse(r#"select#ass_id option[selected="selected"]"#)
@@ -76,41 +109,65 @@ impl IliasElement for GradePage {
             .select(selected_assignment_dropdown_selector)
             .next()
             .whatever_context("Did not find selected assignment in dropdown")?;
-        let ass_id = assignment_selection
-            .attr("value")
-            .whatever_context("Dropdown entry did not have a value")?
-            .to_string();
+        let ass_id = AssignmentId::new(
+            assignment_selection
+                .attr("value")
+                .whatever_context("Dropdown entry did not have a value")?,
+        );
         let name = assignment_selection.text().collect();
 
-        let toolbar_form_querypath = element
-            .select(toolbar_form_selector)
-            .next()
-            .whatever_context("Did not find toolbar form")?
-            .attr("action")
-            .whatever_context("Toolbar form had no action")?
-            .to_string();
-
+        let toolbar_form = HtmlForm::parse(
+            element
+                .select(toolbar_form_selector)
+                .next()
+                .whatever_context("Did not find toolbar form")?,
+        )
+        .whatever_context("Could not parse toolbar form")?;
+        let toolbar_form_querypath = Querypath::parse(&toolbar_form.action);
+
+        // Large lectures have more members than fit on one page of the table
+        let all_pages = table::all_pages(ilias_client, element, "exc_mem")?;
+        let pages = match &all_pages {
+            Some(pages) => pages.iter().map(Html::root_element).collect(),
+            None => vec![element],
+        };
+
+        let mut warnings = Warnings::new(mode);
         let mut submissions = vec![];
-        for submission_element in element.select(submission_row_selector) {
-            if let Some(submission) = GradeSubmission::parse(submission_element)
-                .whatever_context("Could not parse submission")?
-            {
-                submissions.push(submission);
+        let mut members = vec![];
+        for page in pages {
+            for submission_element in page.select(submission_row_selector) {
+                members.extend(ExerciseMember::parse(submission_element));
+                // Rows of unassigned users parse to `None`
+                if let Some(Some(submission)) = warnings.check(
+                    GradeSubmission::parse(submission_element),
+                    submission_element,
+                    submission_row_selector,
+                    "Could not parse submission",
+                )? {
+                    submissions.push(submission);
+                }
             }
         }
 
-        Ok(GradePage {
+        Ok(warnings.finish(GradePage {
             name,
             ass_id,
             toolbar_form_querypath,
+            toolbar_form,
             submissions,
-        })
+            members,
+        }))
     }
-}
 
-static NOTIFICATION_ITEM_BUTTON_SELECTOR: OnceLock<Selector> = OnceLock::new();
+    pub fn ass_id(&self) -> &AssignmentId {
+        &self.ass_id
+    }
+
+    pub fn toolbar_form_querypath(&self) -> &Querypath {
+        &self.toolbar_form_querypath
+    }
 
-impl GradePage {
     pub fn download_all_submissions_zip(
         &self,
         ilias_client: &IliasClient,
@@ -121,34 +178,26 @@ impl GradePage {
             ("user_login", ""),
             ("cmd[downloadSubmissions]", ":)"),
         ];
+        // Earlier downloads of this assignment may still be listed
+        let known_tasks = ilias_client.background_tasks()?;
         let response =
             ilias_client.post_querypath_form(&self.toolbar_form_querypath, &form_data)?;
         let html = Html::parse_document(&ilias_client.get_text(response)?);
 
-        let notification_item_button_selector = NOTIFICATION_ITEM_BUTTON_SELECTOR.get_or_init(|| Selector::parse(".il-aggregate-notifications .il-notification-item .media-body .il-item-notification-title button").expect("Could not parse selector"));
-        let from_url_regex =
-            Regex::new("from_url=(?<url>[^&]+)&").whatever_context("Unable to parse regex")?;
-        let dowload_querypath = html
-            .select(notification_item_button_selector)
-            .map(|button| button.attr("data-action").expect("Button had no action"))
-            .find_map(|querypath| {
-                let form_url = from_url_regex.captures(querypath)?.name("url")?.as_str();
-                let form_url = String::from_utf8(
-                    base64::prelude::BASE64_URL_SAFE_NO_PAD
-                        .decode(form_url)
-                        .ok()?,
-                )
-                .ok()?;
-
-                if form_url.contains(&self.ass_id) {
-                    Some(querypath)
-                } else {
-                    None
-                }
-            })
+        // Large exercises take a while to zip, so the task may still be running
+        let task = ilias_client
+            .wait_for_background_task(
+                Some(&html),
+                &known_tasks,
+                |task| task.was_started_with_param("ass_id", self.ass_id.as_str()),
+                &PollOptions::default(),
+            )
             .whatever_context("Could not find download querypath")?;
+        if task.state == BackgroundTaskState::Failed {
+            whatever!("ILIAS failed to zip the submissions")
+        }
 
-        ilias_client.download_file(dowload_querypath, to)?;
+        task.download(ilias_client, to)?;
 
         Ok(())
     }
@@ -176,13 +225,13 @@ impl GradePage {
         let mut form_data = form_data.map(|(a, b)| (a.to_string(), b)).to_vec();
 
         for submission in changed_submissions {
-            form_data.push(("sel_part_ids[]".to_string(), &submission.ilias_id));
-            form_data.push(("listed_part_ids[]".to_string(), &submission.ilias_id));
-            form_data.push((format!("status[{}]", &submission.ilias_id), "notgraded"));
+            form_data.push(("sel_part_ids[]".to_string(), submission.ilias_id.as_str()));
             form_data.push((
-                format!("mark[{}]", &submission.ilias_id),
-                &submission.points,
+                "listed_part_ids[]".to_string(),
+                submission.ilias_id.as_str(),
             ));
+            form_data.push((format!("status[{}]", submission.ilias_id), "notgraded"));
+            form_data.push((format!("mark[{}]", submission.ilias_id), &submission.points));
         }
         ilias_client.post_querypath_form(&self.toolbar_form_querypath, &form_data)?;
         Ok(())
@@ -9,16 +9,21 @@ use snafu::{OptionExt, ResultExt, Whatever, whatever};
 
 use crate::{
     client::{AddFileWithFilename, IliasClient},
+    exercise::assignment::read_submission_text,
+    id::MemberId,
     local_file::NamedLocalFile,
+    querypath::Querypath,
 };
 
 /// A submission of a user or team for an assignment that feedback can be uploaded to.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GradeSubmission {
     pub identifier: String,
-    pub file_feedback_querypath: String,
-    pub ilias_id: String,
+    pub file_feedback_querypath: Querypath,
+    pub ilias_id: MemberId,
     pub points: String,
+    text_querypath: Option<Querypath>,
 }
 impl Display for GradeSubmission {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -38,6 +43,22 @@ static POST_UPLOAD_FEEDBACK_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static UPLOAD_POST_SCRIPT_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static UPLOAD_POST_REGEX: OnceLock<Regex> = OnceLock::new();
 
+/// `Team <number>` for a team rendered as `(<number>)`, `None` for members without a team
+pub(super) fn team_identifier(team_id: &str) -> Result<Option<String>, Whatever> {
+    if !team_id.contains("(") && !team_id.contains(")") {
+        return Ok(None);
+    }
+    let team_id = team_id
+        .trim()
+        .strip_prefix("(")
+        .whatever_context(format!("Unexpected team id (no prefix '(') {team_id}"))?;
+    let team_id = team_id
+        .strip_suffix(")")
+        .whatever_context(format!("Unexpected team id (no suffix ')') {team_id}"))?;
+
+    Ok(Some(format!("Team {team_id}")))
+}
+
 impl GradeSubmission {
     /// Construct a submission from it's table row element.
     pub fn parse(element: ElementRef) -> Result<Option<GradeSubmission>, Whatever> {
@@ -63,20 +84,12 @@ impl GradeSubmission {
         });
 
         let identifier = if let Some(team_id_element) = element.select(team_id_selector).next() {
-            let team_id = team_id_element.text().collect::<String>();
-            if !team_id.contains("(") && !team_id.contains(")") {
+            let Some(identifier) = team_identifier(&team_id_element.text().collect::<String>())?
+            else {
                 debug!("Unassiged user");
                 return Ok(None);
-            }
-            let team_id = team_id
-                .trim()
-                .strip_prefix("(")
-                .whatever_context(format!("Unexpected team id (no prefix '(') {team_id}"))?;
-            let team_id = team_id
-                .strip_suffix(")")
-                .whatever_context(format!("Unexpected team id (no suffix ')') {team_id}"))?;
-
-            format!("Team {team_id}")
+            };
+            identifier
         } else if let Some(signin_name_element) = element.select(signin_name_selector).next()
             && signin_name_element.text().collect::<String>().contains("@")
             && let Some(name_element) = element.select(name_selector).next()
@@ -101,7 +114,10 @@ impl GradeSubmission {
         let ilias_id = element
             .select(ilias_id_selector)
             .next()
-            .expect("Could not find ilais id");
+            .whatever_context(format!("Did not find ilias id for {identifier}"))?
+            .value()
+            .attr("value")
+            .whatever_context(format!("Ilias id of {identifier} has no value"))?;
 
         let points = element
             .select(points_selector)
@@ -122,20 +138,36 @@ impl GradeSubmission {
             .whatever_context(format!(
                 "Did not find file feedback querypath for {identifier}"
             ))?
-            .to_string();
+            .into();
+        // Only text assignments offer to show the submitted text
+        let text_querypath = element
+            .select(dropdown_action_selector)
+            .filter_map(|button| button.attr("data-action"))
+            .find(|&querypath| querypath.contains("cmd=showAssignmentText"))
+            .map(Querypath::parse);
 
         Ok(Some(GradeSubmission {
             identifier,
             file_feedback_querypath: feedback_querypath,
-            ilias_id: ilias_id
-                .value()
-                .attr("value")
-                .expect("Attribute value not found")
-                .to_owned(),
+            ilias_id: MemberId::new(ilias_id),
             points,
+            text_querypath,
         }))
     }
 
+    pub fn text_querypath(&self) -> Option<&Querypath> {
+        self.text_querypath.as_ref()
+    }
+
+    /// The submitted text as html, if this is a submission to a text assignment
+    pub fn text(&self, ilias_client: &IliasClient) -> Result<String, Whatever> {
+        let text_querypath = self
+            .text_querypath
+            .as_ref()
+            .whatever_context(format!("{} has no text submission", self.identifier))?;
+        read_submission_text(ilias_client, text_querypath)
+    }
+
     pub fn upload(&self, file: NamedLocalFile, ilias_client: &IliasClient) -> Result<(), Whatever> {
         debug!("Uploading {:?} to {:?}", file, self);
         let upload_feedback_form_selector = UPLOAD_FEEDBACK_FORM_SELECTOR.get_or_init(|| {
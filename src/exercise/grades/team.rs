@@ -0,0 +1,581 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    io::Read,
+    sync::OnceLock,
+};
+
+use log::{debug, info};
+use scraper::{ElementRef, Selector};
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use super::{GradePage, submission::team_identifier};
+use crate::{
+    client::IliasClient,
+    exercise::assignment::team::{add_team_member, remove_team_members},
+    id::MemberId,
+    querypath::Querypath,
+};
+
+/// A row of the member table of an assignment as seen by tutors
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExerciseMember {
+    pub id: MemberId,
+    pub name: String,
+    pub login: Option<String>,
+    /// The team of the member for team assignments, like
+    /// [`GradeSubmission::identifier`](super::submission::GradeSubmission::identifier)
+    pub team: Option<String>,
+    team_querypath: Option<Querypath>,
+}
+
+/// The desired teams of an assignment, read from a CSV file with the columns `team` and `login`
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TeamRoster {
+    pub entries: Vec<RosterEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RosterEntry {
+    pub team: String,
+    pub login: String,
+}
+
+/// A change needed to bring the teams of an assignment in line with a [`TeamRoster`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RosterChange {
+    /// Create a new team from members, taking them out of their current teams first
+    CreateTeam { label: String, logins: Vec<String> },
+    /// Move a member into an existing team
+    Move {
+        login: String,
+        from: Option<String>,
+        to: String,
+    },
+}
+
+/// The changes a [`TeamRoster`] would make. Members missing from the roster are left untouched.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RosterDiff {
+    pub changes: Vec<RosterChange>,
+    /// Logins of the roster that are not members of the assignment
+    pub unknown_logins: Vec<String>,
+}
+
+static MEMBER_CHECKBOX_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static MEMBER_NAME_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static MEMBER_TEAM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static MEMBER_LOGIN_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TEAM_ACTION_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+impl ExerciseMember {
+    /// Construct a member from its row in the member table, `None` for rows without a member
+    pub(super) fn parse(row: ElementRef) -> Option<ExerciseMember> {
+        let member_checkbox_selector = MEMBER_CHECKBOX_SELECTOR.get_or_init(|| {
+            Selector::parse("td:nth-child(1) > input:nth-child(1)")
+                .expect("Could not parse selector")
+        });
+        let member_name_selector = MEMBER_NAME_SELECTOR
+            .get_or_init(|| Selector::parse("td:nth-child(2)").expect("Could not parse selector"));
+        let member_team_selector = MEMBER_TEAM_SELECTOR.get_or_init(|| {
+            Selector::parse("td:nth-child(2) div.small").expect("Could not parse selector")
+        });
+        let member_login_selector = MEMBER_LOGIN_SELECTOR.get_or_init(|| {
+            Selector::parse("td:nth-child(3).std").expect("Could not parse selector")
+        });
+        let team_action_selector = TEAM_ACTION_SELECTOR.get_or_init(|| {
+            Selector::parse(r#".dropdown-menu [data-action*="ilexsubmissionteamgui"]"#)
+                .expect("Could not parse selector")
+        });
+
+        let id = row.select(member_checkbox_selector).next()?.attr("value")?;
+        // The team number is rendered below the name
+        let name = row
+            .select(member_name_selector)
+            .next()?
+            .text()
+            .map(str::trim)
+            .find(|text| !text.is_empty())?
+            .to_string();
+        let login = row
+            .select(member_login_selector)
+            .next()
+            .map(|login| login.text().collect::<String>().trim().to_string())
+            .filter(|login| !login.is_empty());
+        let team = row
+            .select(member_team_selector)
+            .next()
+            .and_then(|team| team_identifier(&team.text().collect::<String>()).ok()?);
+        let team_querypath = row
+            .select(team_action_selector)
+            .next()
+            .and_then(|action| action.attr("data-action"))
+            .map(Querypath::parse);
+
+        Some(ExerciseMember {
+            id: MemberId::new(id),
+            name,
+            login,
+            team,
+            team_querypath,
+        })
+    }
+
+    pub fn team_querypath(&self) -> Option<&Querypath> {
+        self.team_querypath.as_ref()
+    }
+
+    fn require_team_querypath(&self) -> Result<&Querypath, Whatever> {
+        self.team_querypath
+            .as_ref()
+            .whatever_context(format!("Did not find team management for {}", self.name))
+    }
+}
+
+impl Display for ExerciseMember {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.login {
+            Some(login) => write!(f, "{} ({login})", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl TeamRoster {
+    pub fn from_csv<R: Read>(reader: R) -> Result<TeamRoster, Whatever> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+        let entries = reader
+            .deserialize()
+            .collect::<Result<Vec<RosterEntry>, _>>()
+            .whatever_context("Could not read team roster")?;
+        debug!("Roster: {entries:?}");
+        Ok(TeamRoster { entries })
+    }
+
+    /// The logins of each team, in order of first appearance
+    fn teams(&self) -> Vec<(&str, Vec<&str>)> {
+        let mut teams: Vec<(&str, Vec<&str>)> = vec![];
+        for entry in &self.entries {
+            match teams.iter_mut().find(|(label, _)| *label == entry.team) {
+                Some((_, logins)) => logins.push(&entry.login),
+                None => teams.push((&entry.team, vec![&entry.login])),
+            }
+        }
+        teams
+    }
+}
+
+impl Display for RosterChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RosterChange::CreateTeam { label, logins } => {
+                write!(f, "Create team {label} with {}", logins.join(", "))
+            }
+            RosterChange::Move {
+                login,
+                from: Some(from),
+                to,
+            } => write!(f, "Move {login} from {from} to {to}"),
+            RosterChange::Move {
+                login,
+                from: None,
+                to,
+            } => write!(f, "Add {login} to {to}"),
+        }
+    }
+}
+
+impl RosterDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Display for RosterDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        for login in &self.unknown_logins {
+            writeln!(f, "Unknown member {login}")?;
+        }
+        Ok(())
+    }
+}
+
+impl GradePage {
+    /// All members of the assignment, including those without a submission
+    pub fn members(&self) -> &[ExerciseMember] {
+        &self.members
+    }
+
+    /// The members of each team, by team identifier
+    pub fn teams(&self) -> BTreeMap<&str, Vec<&ExerciseMember>> {
+        let mut teams: BTreeMap<&str, Vec<&ExerciseMember>> = BTreeMap::new();
+        for member in &self.members {
+            if let Some(team) = &member.team {
+                teams.entry(team).or_default().push(member);
+            }
+        }
+        teams
+    }
+
+    /// The members that are not in any team yet
+    pub fn members_without_team(&self) -> Vec<&ExerciseMember> {
+        self.members
+            .iter()
+            .filter(|member| member.team.is_none())
+            .collect()
+    }
+
+    pub fn member_by_login(&self, login: &str) -> Option<&ExerciseMember> {
+        self.members
+            .iter()
+            .find(|member| member.login.as_deref() == Some(login))
+    }
+
+    /// Create a team of `members`, who must not be in a team yet
+    pub fn create_team(
+        &self,
+        ilias_client: &IliasClient,
+        members: &[&ExerciseMember],
+    ) -> Result<(), Whatever> {
+        if let Some(member) = members.iter().find(|member| member.team.is_some()) {
+            whatever!("{member} already is in a team")
+        }
+
+        // The command select and its button are rendered above and below the table
+        let (select, button) = [
+            ("selected_cmd", "select_cmd"),
+            ("selected_cmd2", "select_cmd2"),
+        ]
+        .into_iter()
+        .find(|(select, button)| {
+            self.toolbar_form.button(button).is_some()
+                && self
+                    .toolbar_form
+                    .select_options(select)
+                    .iter()
+                    .any(|option| option.value == "createTeams")
+        })
+        .whatever_context(format!("Teams can not be created for {}", self.name))?;
+
+        let mut form = self.toolbar_form.clone();
+        form.set(select, "createTeams");
+        // The listed members are only part of the form if it contains the table
+        let add_listed = form.get("listed_part_ids[]").is_none();
+        for member in members {
+            form.add("sel_part_ids[]", member.id.as_str());
+            if add_listed {
+                form.add("listed_part_ids[]", member.id.as_str());
+            }
+        }
+        let response = form
+            .submit_with_button(ilias_client, button)
+            .whatever_context("Could not create team")?;
+        if ilias_client
+            .is_alert_response(response)
+            .whatever_context("Could not check error state of response")?
+        {
+            whatever!("ILIAS refused to create a team for {}", self.name)
+        }
+        info!(
+            "Created team of {} members for {}",
+            members.len(),
+            self.name
+        );
+        Ok(())
+    }
+
+    /// Move `member` out of their current team into the team of `team_member`
+    pub fn move_member(
+        &self,
+        ilias_client: &IliasClient,
+        member: &ExerciseMember,
+        team_member: &ExerciseMember,
+    ) -> Result<(), Whatever> {
+        let login = member
+            .login
+            .as_deref()
+            .whatever_context(format!("{member} has no login to add them to a team"))?;
+        if team_member.team.is_none() {
+            whatever!("{team_member} is not in a team")
+        }
+
+        if member.team.is_some() {
+            remove_team_members(
+                ilias_client,
+                member.require_team_querypath()?,
+                &[&member.id],
+            )
+            .whatever_context(format!("Could not remove {member} from their team"))?;
+        }
+        add_team_member(ilias_client, team_member.require_team_querypath()?, login)
+            .whatever_context(format!(
+                "Could not add {member} to the team of {team_member}"
+            ))?;
+        info!("Moved {member} to the team of {team_member}");
+        Ok(())
+    }
+
+    /// Compare the current teams with `roster`, matching each roster team with the existing team
+    /// that contains most of its members
+    pub fn diff_roster(&self, roster: &TeamRoster) -> RosterDiff {
+        let mut diff = RosterDiff::default();
+        let mut claimed_teams: HashSet<&str> = HashSet::new();
+
+        for (label, logins) in roster.teams() {
+            let mut members = vec![];
+            for login in logins {
+                match self.member_by_login(login) {
+                    Some(member) => members.push(member),
+                    None => diff.unknown_logins.push(login.to_string()),
+                }
+            }
+
+            let mut team_counts: HashMap<&str, usize> = HashMap::new();
+            for team in members.iter().filter_map(|member| member.team.as_deref()) {
+                *team_counts.entry(team).or_default() += 1;
+            }
+            let target_team = team_counts
+                .into_iter()
+                .filter(|(team, _)| !claimed_teams.contains(team))
+                .max_by(|(team_a, count_a), (team_b, count_b)| {
+                    count_a.cmp(count_b).then(team_b.cmp(team_a))
+                })
+                .map(|(team, _)| team);
+
+            match target_team {
+                Some(team) => {
+                    claimed_teams.insert(team);
+                    for member in members {
+                        if member.team.as_deref() != Some(team) {
+                            diff.changes.push(RosterChange::Move {
+                                login: member.login.clone().unwrap_or_default(),
+                                from: member.team.clone(),
+                                to: team.to_string(),
+                            });
+                        }
+                    }
+                }
+                None if !members.is_empty() => diff.changes.push(RosterChange::CreateTeam {
+                    label: label.to_string(),
+                    logins: members
+                        .iter()
+                        .filter_map(|member| member.login.clone())
+                        .collect(),
+                }),
+                None => {}
+            }
+        }
+        debug!("Roster diff: {diff:?}");
+        diff
+    }
+
+    /// Apply the changes of [`GradePage::diff_roster`]
+    pub fn apply_roster(
+        &self,
+        ilias_client: &IliasClient,
+        diff: &RosterDiff,
+    ) -> Result<(), Whatever> {
+        let moved_logins: HashSet<&str> = diff
+            .changes
+            .iter()
+            .flat_map(|change| match change {
+                RosterChange::CreateTeam { logins, .. } => {
+                    logins.iter().map(String::as_str).collect::<Vec<_>>()
+                }
+                RosterChange::Move { login, .. } => vec![login.as_str()],
+            })
+            .collect();
+
+        for change in &diff.changes {
+            info!("{change}");
+            match change {
+                RosterChange::Move { login, to, .. } => {
+                    let member = self
+                        .member_by_login(login)
+                        .whatever_context(format!("Unknown member {login}"))?;
+                    // Someone staying in the target team, whose team page stays valid
+                    let team_member = self
+                        .members
+                        .iter()
+                        .find(|team_member| {
+                            team_member.team.as_deref() == Some(to)
+                                && team_member
+                                    .login
+                                    .as_deref()
+                                    .is_none_or(|login| !moved_logins.contains(login))
+                        })
+                        .whatever_context(format!("Team {to} has no remaining members"))?;
+                    self.move_member(ilias_client, member, team_member)?;
+                }
+                RosterChange::CreateTeam { logins, .. } => {
+                    let members = logins
+                        .iter()
+                        .map(|login| {
+                            self.member_by_login(login)
+                                .whatever_context(format!("Unknown member {login}"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    for member in members.iter().filter(|member| member.team.is_some()) {
+                        remove_team_members(
+                            ilias_client,
+                            member.require_team_querypath()?,
+                            &[&member.id],
+                        )
+                        .whatever_context(format!("Could not remove {member} from their team"))?;
+                    }
+                    // The members are in no team anymore after removing them
+                    let members: Vec<ExerciseMember> = members
+                        .into_iter()
+                        .map(|member| ExerciseMember {
+                            team: None,
+                            ..member.clone()
+                        })
+                        .collect();
+                    self.create_team(ilias_client, &members.iter().collect::<Vec<_>>())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scraper::Html;
+
+    use super::*;
+    use crate::{form::HtmlForm, id::AssignmentId};
+
+    fn member(login: &str, team: Option<&str>) -> ExerciseMember {
+        ExerciseMember {
+            id: MemberId::new(login),
+            name: login.to_string(),
+            login: Some(login.to_string()),
+            team: team.map(str::to_string),
+            team_querypath: None,
+        }
+    }
+
+    fn grade_page(members: Vec<ExerciseMember>) -> GradePage {
+        let html = Html::parse_fragment(r#"<form action="ilias.php?cmd=post"></form>"#);
+        let form = html
+            .select(&Selector::parse("form").unwrap())
+            .next()
+            .unwrap();
+        GradePage {
+            name: "Blatt 1".to_string(),
+            ass_id: AssignmentId::new("1"),
+            toolbar_form_querypath: Querypath::parse("ilias.php?cmd=post"),
+            toolbar_form: HtmlForm::parse(form).unwrap(),
+            submissions: vec![],
+            members,
+        }
+    }
+
+    fn roster(csv: &str) -> TeamRoster {
+        TeamRoster::from_csv(csv.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reads_roster_columns_by_header() {
+        let roster = roster("login, team\nalice, A\nbob, A\ncarol, B\n");
+        let teams = roster.teams();
+        assert_eq!(
+            teams,
+            vec![("A", vec!["alice", "bob"]), ("B", vec!["carol"])]
+        );
+    }
+
+    #[test]
+    fn rejects_roster_without_team_column() {
+        assert!(TeamRoster::from_csv("login\nalice\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn matching_teams_need_no_changes() {
+        let page = grade_page(vec![
+            member("alice", Some("Team 1")),
+            member("bob", Some("Team 1")),
+        ]);
+        let diff = page.diff_roster(&roster("team,login\nA,alice\nA,bob\n"));
+        assert!(diff.is_empty());
+        assert!(diff.unknown_logins.is_empty());
+    }
+
+    #[test]
+    fn moves_members_into_the_team_of_the_majority() {
+        let page = grade_page(vec![
+            member("alice", Some("Team 1")),
+            member("bob", Some("Team 1")),
+            member("carol", Some("Team 2")),
+            member("dave", None),
+        ]);
+        let diff = page.diff_roster(&roster("team,login\nA,alice\nA,bob\nA,carol\nA,dave\n"));
+        assert_eq!(
+            diff.changes,
+            vec![
+                RosterChange::Move {
+                    login: "carol".to_string(),
+                    from: Some("Team 2".to_string()),
+                    to: "Team 1".to_string(),
+                },
+                RosterChange::Move {
+                    login: "dave".to_string(),
+                    from: None,
+                    to: "Team 1".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn creates_teams_for_members_without_team() {
+        let page = grade_page(vec![member("alice", None), member("bob", None)]);
+        let diff = page.diff_roster(&roster("team,login\nA,alice\nA,bob\nA,eve\n"));
+        assert_eq!(
+            diff.changes,
+            vec![RosterChange::CreateTeam {
+                label: "A".to_string(),
+                logins: vec!["alice".to_string(), "bob".to_string()],
+            }]
+        );
+        assert_eq!(diff.unknown_logins, vec!["eve".to_string()]);
+    }
+
+    #[test]
+    fn claims_each_existing_team_once() {
+        let page = grade_page(vec![
+            member("alice", Some("Team 1")),
+            member("bob", Some("Team 1")),
+        ]);
+        let diff = page.diff_roster(&roster("team,login\nA,alice\nB,bob\n"));
+        assert_eq!(
+            diff.changes,
+            vec![RosterChange::CreateTeam {
+                label: "B".to_string(),
+                logins: vec!["bob".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_team_identifiers() {
+        assert_eq!(
+            team_identifier(" (12) ").unwrap(),
+            Some("Team 12".to_string())
+        );
+        assert_eq!(team_identifier("").unwrap(), None);
+        assert!(team_identifier("(12").is_err());
+    }
+}
@@ -1,15 +1,261 @@
-use std::fmt::Display;
+use std::{fmt::Display, fs, path::Path, sync::OnceLock};
 
 use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use log::{debug, info};
+use regex::Regex;
+use reqwest::multipart::Form;
+use scraper::{ElementRef, Selector, selectable::Selectable};
+use snafu::{OptionExt, ResultExt, Snafu, Whatever, whatever};
+
+use super::{
+    client::IliasClient, id::FileId, local_file::NamedLocalFile, parse_date, querypath::Querypath,
+    table::TableRow,
+};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code)]
 pub struct File {
     pub name: String,
     pub description: String,
     pub date: Option<DateTime<Local>>,
-    pub download_querypath: Option<String>,
-    pub id: Option<String>,
+    pub download_querypath: Option<Querypath>,
+    pub id: Option<FileId>,
+    /// In bytes, as far as ILIAS shows it. Rounded to the unit ILIAS displays.
+    pub size: Option<u64>,
+}
+
+/// An entry of the version history of a [`File`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileVersion {
+    pub version: Option<u32>,
+    pub name: String,
+    pub date: Option<DateTime<Local>>,
+    pub author: Option<String>,
+    pub download_querypath: Option<Querypath>,
+}
+
+/// How a new version of a file is uploaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VersionMode {
+    /// Add the upload as a new version, keeping the current one in the history
+    Add,
+    /// Replace the current version with the upload
+    Replace,
+}
+
+static CONTENT_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static VERSION_HEADER_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static VERSION_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static VERSION_DOWNLOAD_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static ADD_VERSION_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static REPLACE_VERSION_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+static FILE_SIZE_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// Parse a file size like `1,2 MB` or `345 Bytes` into bytes
+pub(crate) fn parse_file_size(text: &str) -> Option<u64> {
+    let file_size_regex = FILE_SIZE_REGEX.get_or_init(|| {
+        Regex::new(r"(?i)^\s*(?<size>\d+([.,]\d+)?)\s*(?<unit>[kmg]i?b|bytes?)\s*$")
+            .expect("Could not parse regex")
+    });
+
+    let captures = file_size_regex.captures(text)?;
+    let size: f64 = captures["size"].replace(',', ".").parse().ok()?;
+    let factor = match captures["unit"].to_lowercase().chars().next()? {
+        'k' => 1024.0,
+        'm' => 1024.0 * 1024.0,
+        'g' => 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+    Some((size * factor) as u64)
+}
+
+/// A way in which the files ILIAS lists after an upload differ from the uploaded ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UploadDiscrepancy {
+    Missing {
+        name: String,
+    },
+    Unexpected {
+        name: String,
+    },
+    SizeMismatch {
+        name: String,
+        sent: u64,
+        listed: u64,
+    },
+}
+
+/// The files ILIAS lists after an upload differ from the uploaded ones
+#[derive(Debug, Snafu)]
+#[snafu(display(
+    "Uploaded files differ from the sent ones: {}",
+    discrepancies.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+))]
+pub struct UploadVerificationError {
+    pub discrepancies: Vec<UploadDiscrepancy>,
+}
+
+/// Match the `sent` files to the `listed` files that appeared after uploading them
+pub(crate) fn verify_uploads(
+    sent: &[NamedLocalFile],
+    mut listed: Vec<File>,
+) -> Result<Vec<File>, UploadVerificationError> {
+    let mut discrepancies = vec![];
+    let mut uploaded = vec![];
+    for file in sent {
+        let Some(position) = listed.iter().position(|listed| listed.name == file.name) else {
+            discrepancies.push(UploadDiscrepancy::Missing {
+                name: file.name.clone(),
+            });
+            continue;
+        };
+        let listed_file = listed.remove(position);
+
+        if let (Some(listed_size), Ok(metadata)) = (listed_file.size, fs::metadata(&file.path)) {
+            // ILIAS shows sizes with a single decimal, so they are off by up to 5%
+            if listed_size.abs_diff(metadata.len()) * 20 > metadata.len() {
+                discrepancies.push(UploadDiscrepancy::SizeMismatch {
+                    name: file.name.clone(),
+                    sent: metadata.len(),
+                    listed: listed_size,
+                });
+            }
+        }
+        uploaded.push(listed_file);
+    }
+    discrepancies.extend(
+        listed
+            .into_iter()
+            .map(|file| UploadDiscrepancy::Unexpected { name: file.name }),
+    );
+
+    if !discrepancies.is_empty() {
+        return UploadVerificationSnafu { discrepancies }.fail();
+    }
+    debug!("Verified uploads: {uploaded:?}");
+    Ok(uploaded)
+}
+
+impl File {
+    pub fn download(&self, ilias_client: &IliasClient, to: &Path) -> Result<(), Whatever> {
+        let download_querypath = self
+            .download_querypath
+            .as_ref()
+            .whatever_context(format!("File {} has no download querypath", self.name))?;
+        ilias_client.download_file(download_querypath, to)
+    }
+
+    fn versions_querypath(&self) -> Result<String, Whatever> {
+        let id = self
+            .id
+            .as_ref()
+            .and_then(FileId::ref_id)
+            .whatever_context(format!("File {} has no ref id", self.name))?;
+        Ok(format!(
+            "ilias.php?baseClass=ilrepositorygui&ref_id={id}&cmd=versions"
+        ))
+    }
+
+    /// List the version history of this file, newest version first
+    pub fn versions(&self, ilias_client: &IliasClient) -> Result<Vec<FileVersion>, Whatever> {
+        let version_header_selector = VERSION_HEADER_SELECTOR.get_or_init(|| {
+            Selector::parse("#ilContentContainer table thead th").expect("Could not parse selector")
+        });
+        let version_row_selector = VERSION_ROW_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"#ilContentContainer table tr:has(a[href*="hist_id="])"#)
+                .expect("Could not parse selector")
+        });
+
+        let versions_page = ilias_client
+            .get_querypath(&self.versions_querypath()?)
+            .whatever_context(format!("Could not get versions of {}", self.name))?;
+
+        let columns = TableRow::columns(versions_page.root_element(), version_header_selector);
+        debug!("Version columns: {columns:?}");
+
+        let versions = versions_page
+            .select(version_row_selector)
+            .map(|row| FileVersion::parse(row, &columns, ilias_client.timezone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        debug!("Versions of {}: {versions:?}", self.name);
+        Ok(versions)
+    }
+
+    /// Upload `file` as a new version of this file. Unlike deleting and re-uploading the file,
+    /// this keeps the id and with it all links to the file intact.
+    pub fn upload_new_version(
+        &self,
+        ilias_client: &IliasClient,
+        file: &NamedLocalFile,
+        mode: VersionMode,
+    ) -> Result<(), Whatever> {
+        let add_version_selector = ADD_VERSION_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"[href*="cmd=addNewVersion"], [data-action*="cmd=addNewVersion"]"#)
+                .expect("Could not parse selector")
+        });
+        let replace_version_selector = REPLACE_VERSION_SELECTOR.get_or_init(|| {
+            Selector::parse(
+                r#"[href*="cmd=addReplacingVersion"], [data-action*="cmd=addReplacingVersion"]"#,
+            )
+            .expect("Could not parse selector")
+        });
+        let content_form_selector = CONTENT_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse("#ilContentContainer form").expect("Could not parse selector")
+        });
+
+        let versions_page = ilias_client
+            .get_querypath(&self.versions_querypath()?)
+            .whatever_context(format!("Could not get versions of {}", self.name))?;
+        let version_button_selector = match mode {
+            VersionMode::Add => add_version_selector,
+            VersionMode::Replace => replace_version_selector,
+        };
+        let version_button = versions_page
+            .select(version_button_selector)
+            .next()
+            .whatever_context(format!("You can not upload a new version of {}", self.name))?;
+        let version_form_querypath = version_button
+            .attr("href")
+            .or(version_button.attr("data-action"))
+            .whatever_context("Version button had no target")?;
+
+        let version_form_page = ilias_client.get_querypath(version_form_querypath)?;
+        let finish_upload_querypath = version_form_page
+            .select(content_form_selector)
+            .next()
+            .whatever_context("Could not find new version form")?
+            .attr("action")
+            .whatever_context("New version form had no action")?;
+        debug!("Finish version upload querypath: {finish_upload_querypath}");
+
+        let upload_querypath = ilias_client.file_input_upload_querypath(&version_form_page)?;
+        let file_id = ilias_client.upload_to_file_input(&upload_querypath, &file.path)?;
+
+        let finish_form = Form::new()
+            .text("form/input_0/input_1", file.name.clone()) // Title
+            .text("form/input_0/input_2", "") // Description
+            .text("form/input_0/input_3[]", file_id) // File id
+            .percent_encode_noop();
+        let response =
+            ilias_client.post_querypath_multipart(finish_upload_querypath, finish_form)?;
+        if ilias_client
+            .is_alert_response(response)
+            .whatever_context("Could not check error state of response")?
+        {
+            whatever!(
+                "New version response has an error, please check if the version was uploaded and report"
+            )
+        }
+
+        info!("Uploaded {} as new version of {}", file.name, self.name);
+        Ok(())
+    }
 }
 
 impl Display for File {
@@ -17,3 +263,147 @@ impl Display for File {
         write!(f, "{}", self.name)
     }
 }
+
+impl Display for UploadDiscrepancy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadDiscrepancy::Missing { name } => write!(f, "{name} is missing"),
+            UploadDiscrepancy::Unexpected { name } => {
+                write!(f, "{name} appeared without being sent")
+            }
+            UploadDiscrepancy::SizeMismatch { name, sent, listed } => {
+                write!(
+                    f,
+                    "{name} was sent with {sent} bytes, but has {listed} bytes"
+                )
+            }
+        }
+    }
+}
+
+impl FileVersion {
+    fn parse(row: ElementRef, columns: &[String], timezone: Tz) -> Result<FileVersion, Whatever> {
+        let version_download_selector = VERSION_DOWNLOAD_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"a[href*="hist_id="]"#).expect("Could not parse selector")
+        });
+
+        let table_row = TableRow::parse(row, columns);
+        let name = table_row
+            .value(&["Dateiname", "Filename", "Titel", "Title"])
+            .whatever_context("Could not find name of file version")?
+            .to_string();
+        let version = table_row
+            .value(&["Version", "Vers."])
+            .and_then(|version| version.parse().ok());
+        let date = table_row
+            .value(&["Datum", "Date"])
+            .and_then(|date| parse_date(date, timezone).ok());
+        let author = table_row
+            .value(&["Benutzer", "User", "Erstellt von", "Created by"])
+            .cloned();
+        let download_querypath = row
+            .select(version_download_selector)
+            .next()
+            .and_then(|link| link.attr("href"))
+            .map(Querypath::parse);
+
+        Ok(FileVersion {
+            version,
+            name,
+            date,
+            author,
+            download_querypath,
+        })
+    }
+
+    pub fn download(&self, ilias_client: &IliasClient, to: &Path) -> Result<(), Whatever> {
+        let download_querypath = self
+            .download_querypath
+            .as_ref()
+            .whatever_context(format!("Version {} has no download querypath", self.name))?;
+        ilias_client.download_file(download_querypath, to)
+    }
+}
+
+impl Display for FileVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.version {
+            Some(version) => write!(f, "{} (version {version})", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_file::TestFiles;
+
+    fn listed(name: &str, size: Option<u64>) -> File {
+        File {
+            name: name.to_string(),
+            description: String::new(),
+            date: None,
+            download_querypath: None,
+            id: None,
+            size,
+        }
+    }
+
+    #[test]
+    fn parses_file_sizes() {
+        assert_eq!(parse_file_size("345 Bytes"), Some(345));
+        assert_eq!(parse_file_size("1 Byte"), Some(1));
+        assert_eq!(parse_file_size("1,5 KB"), Some(1536));
+        assert_eq!(parse_file_size(" 2.25 MiB "), Some(2359296));
+        assert_eq!(parse_file_size("1 GB"), Some(1073741824));
+        assert_eq!(parse_file_size("12 Dateien"), None);
+        assert_eq!(parse_file_size(""), None);
+    }
+
+    #[test]
+    fn verifies_matching_uploads() {
+        let test_files = TestFiles::new();
+        let files = [
+            test_files.file("a.txt", &[0; 2000]),
+            test_files.file("b.txt", b""),
+        ];
+        let uploaded = verify_uploads(
+            &files,
+            vec![listed("b.txt", None), listed("a.txt", Some(1946))],
+        )
+        .unwrap();
+        let names: Vec<&str> = uploaded.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn reports_all_discrepancies() {
+        let test_files = TestFiles::new();
+        let files = [
+            test_files.file("c.txt", &[0; 100]),
+            test_files.file("d.txt", b""),
+        ];
+        let err = verify_uploads(
+            &files,
+            vec![listed("c.txt", Some(200)), listed("e.txt", None)],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.discrepancies,
+            vec![
+                UploadDiscrepancy::SizeMismatch {
+                    name: "c.txt".to_string(),
+                    sent: 100,
+                    listed: 200,
+                },
+                UploadDiscrepancy::Missing {
+                    name: "d.txt".to_string(),
+                },
+                UploadDiscrepancy::Unexpected {
+                    name: "e.txt".to_string(),
+                },
+            ]
+        );
+    }
+}
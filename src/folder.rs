@@ -1,68 +1,176 @@
 use std::{fmt::Display, sync::OnceLock};
 
+use chrono_tz::Tz;
 use log::{debug, info};
 use regex::Regex;
 use reqwest::{Url, multipart::Form};
-use scraper::{ElementRef, Selector, element_ref::Select, selectable::Selectable};
+use scraper::{ElementRef, Html, Selector, element_ref::Select, selectable::Selectable};
 use serde::{Deserialize, Serialize};
 use snafu::{OptionExt, ResultExt, Whatever, whatever};
 
 use super::{
-    IliasElement, Querypath, client::IliasClient, file::File, local_file::NamedLocalFile,
+    IliasElement, Querypath as _,
+    client::IliasClient,
+    file::{File, VersionMode, parse_file_size, verify_uploads},
+    form::{HtmlForm, SelectOption},
+    forum::Forum,
+    id::{FileId, RefId},
+    local_file::NamedLocalFile,
+    opencast::OpencastSeries,
     parse_date,
+    parsed::{ParseMode, Parsed, Warnings},
+    querypath::Querypath,
 };
+use upload::{UnzipMode, UploadOptions, upload_property_fields};
+
+pub mod clipboard;
+pub mod download;
+pub mod properties;
+pub mod trash;
+pub mod tree;
+pub mod upload;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code)]
 pub enum FolderElement {
     File {
         file: File,
-        deletion_querypath: Option<String>,
+        deletion_querypath: Option<Querypath>,
     },
     Exercise {
         name: String,
         description: String,
-        id: String,
-        querypath: String,
-        deletion_querypath: Option<String>,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    Forum {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
     },
     Opencast {
         name: String,
         description: String,
-        id: String,
-        querypath: String,
-        deletion_querypath: Option<String>,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
     },
     Viewable {
         name: String,
         description: String,
-        id: String,
-        querypath: String,
-        deletion_querypath: Option<String>,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    WebLink {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        /// The external url the link points to, if the folder links to it directly. Use
+        /// [`FolderElement::web_link_target`] to resolve it otherwise.
+        target_url: Option<String>,
+        deletion_querypath: Option<Querypath>,
+    },
+    Test {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    Wiki {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    LearningModule {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    Session {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    Survey {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    Poll {
+        name: String,
+        description: String,
+        id: RefId,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
+    },
+    /// An element of a kind that is not supported (yet)
+    Unknown {
+        name: String,
+        description: String,
+        id: Option<RefId>,
+        querypath: Querypath,
+        deletion_querypath: Option<Querypath>,
     },
 }
 
+/// The kind of a [`FolderElement`] without any of its data, e.g. for filtering
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementKind {
+    File,
+    Exercise,
+    Forum,
+    Opencast,
+    Viewable,
+    WebLink,
+    Test,
+    Wiki,
+    LearningModule,
+    Session,
+    Survey,
+    Poll,
+    Unknown,
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code)]
 pub struct Folder {
     name: String,
     description: String,
-    id: String,
+    querypath: Querypath,
     pub elements: Vec<FolderElement>,
-    upload_page_querypath: Option<String>,
+    upload_page_querypath: Option<Querypath>,
+    create_folder_page_querypath: Option<Querypath>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IliasUploadResponse {
     status: u8,
     message: String,
-    file_id: String,
+    pub(crate) file_id: String,
 }
 
 static NAME_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static DESCRIPTION_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static ID_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static UPLOAD_FILE_PAGE_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static CREATE_FOLDER_PAGE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
 static ELEMENT_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static LAST_SCRIPT_SELECTOR: OnceLock<Selector> = OnceLock::new();
@@ -72,7 +180,7 @@ impl IliasElement for Folder {
         Some("fold")
     }
 
-    fn querypath_from_id(id: &str) -> Option<String> {
+    fn querypath_from_id(id: &RefId) -> Option<String> {
         Some(format!(
             "goto.php/{}/{}",
             Self::type_identifier().unwrap(),
@@ -81,6 +189,23 @@ impl IliasElement for Folder {
     }
 
     fn parse(element: ElementRef, ilias_client: &IliasClient) -> Result<Self, Whatever> {
+        Self::parse_with_mode(element, ilias_client, ParseMode::Strict).map(|parsed| parsed.value)
+    }
+
+    fn parse_lenient(
+        element: ElementRef,
+        ilias_client: &IliasClient,
+    ) -> Result<Parsed<Self>, Whatever> {
+        Self::parse_with_mode(element, ilias_client, ParseMode::Lenient)
+    }
+}
+
+impl Folder {
+    fn parse_with_mode(
+        element: ElementRef,
+        ilias_client: &IliasClient,
+        mode: ParseMode,
+    ) -> Result<Parsed<Folder>, Whatever> {
         let name_selector = NAME_SELECTOR.get_or_init(|| {
             Selector::parse(".il-page-content-header").expect("Could not parse selector")
         });
@@ -92,6 +217,9 @@ impl IliasElement for Folder {
         let upload_file_page_selector = UPLOAD_FILE_PAGE_SELECTOR.get_or_init(|| {
             Selector::parse("#il-add-new-item-gl #file").expect("Could not parse selector")
         });
+        let create_folder_page_selector = CREATE_FOLDER_PAGE_SELECTOR.get_or_init(|| {
+            Selector::parse("#il-add-new-item-gl #fold").expect("Could not parse selector")
+        });
 
         let element_selector = ELEMENT_SELECTOR
             .get_or_init(|| Selector::parse(".ilObjListRow").expect("Could not parse selector"));
@@ -113,13 +241,13 @@ impl IliasElement for Folder {
             .whatever_context("Could not find description")?
             .text()
             .collect();
-        let id = element
+        let querypath = element
             .select(id_selector)
             .next()
             .whatever_context("Could not find link in breadcrumbs")?
             .attr("href")
-            .whatever_context("Link missing href attribute")?
-            .to_string();
+            .map(Querypath::parse)
+            .whatever_context("Link missing href attribute")?;
 
         let last_script = element
             .select(last_script_selector)
@@ -128,46 +256,194 @@ impl IliasElement for Folder {
             .text()
             .collect::<String>();
 
+        let mut warnings = Warnings::new(mode);
         let mut elements: Vec<FolderElement> = vec![];
         for element in element.select(element_selector) {
-            let folder_element = FolderElement::parse(element, &last_script, ilias_client)
-                .whatever_context("Could not parse folder element")?;
-            elements.push(folder_element);
+            if let Some(folder_element) = warnings.check(
+                FolderElement::parse(element, &last_script, ilias_client),
+                element,
+                element_selector,
+                "Could not parse folder element",
+            )? {
+                elements.push(folder_element);
+            }
         }
 
         let upload_page_querypath = element
             .select(upload_file_page_selector)
             .next()
             .and_then(|link| link.attr("href"))
-            .map(str::to_string);
+            .map(Querypath::parse);
+        let create_folder_page_querypath = element
+            .select(create_folder_page_selector)
+            .next()
+            .and_then(|link| link.attr("href"))
+            .map(Querypath::parse);
 
         let folder = Folder {
             name,
             description,
-            id,
+            querypath,
             elements,
             upload_page_querypath,
+            create_folder_page_querypath,
         };
         debug!("Folder: {:?}", folder);
 
-        Ok(folder)
+        Ok(warnings.finish(folder))
     }
 }
 
+/// ILIAS UI forms have generated field names, the license select usually is the second input
+fn license_field_name(upload_form: &HtmlForm) -> &str {
+    upload_form
+        .field_name_for_label(&["Lizenz", "License", "Copyright"])
+        .unwrap_or("form/input_1")
+}
+
+/// The per-file unzip checkboxes follow the name, description and file id inputs
+fn unzip_field_names(upload_form: &HtmlForm) -> (&str, &str) {
+    let unzip = upload_form
+        .field_name_for_label(&["Entpacken", "ZIP-Datei entpacken", "Unzip", "Unzip File"])
+        .unwrap_or("form/input_0[input_4][]");
+    let keep_structure = upload_form
+        .field_name_for_label(&[
+            "Struktur übernehmen",
+            "Ordnerstruktur übernehmen",
+            "Take over Structure",
+            "Keep Structure",
+        ])
+        .unwrap_or("form/input_0[input_5][]");
+    (unzip, keep_structure)
+}
+
 static CONTENT_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static CREATE_FOLDER_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static CONFIRM_BUTTON_SELECTOR: OnceLock<Selector> = OnceLock::new();
-static SCRIPT_TAG_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
 impl Folder {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The querypath of this folder, taken from its breadcrumb link
+    pub fn querypath(&self) -> &Querypath {
+        &self.querypath
+    }
+
+    /// The ref id of this folder, extracted from its breadcrumb link
+    pub fn ref_id(&self) -> Option<RefId> {
+        self.querypath.ref_id()
+    }
+
+    pub fn upload_page_querypath(&self) -> Option<&str> {
+        self.upload_page_querypath.as_deref()
+    }
+
+    pub fn create_folder_page_querypath(&self) -> Option<&str> {
+        self.create_folder_page_querypath.as_deref()
+    }
+
+    /// Create a sub-folder and return it
+    pub fn create_folder(
+        &self,
+        ilias_client: &IliasClient,
+        title: &str,
+        description: &str,
+    ) -> Result<Folder, Whatever> {
+        let create_folder_form_selector = CREATE_FOLDER_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"#ilContentContainer form:has(input[name="title"])"#)
+                .expect("Could not parse selector")
+        });
+
+        let create_page = ilias_client.get_querypath(
+            self.create_folder_page_querypath
+                .as_ref()
+                .whatever_context(format!("You can not create folders in {}", self.name))?,
+        )?;
+        let mut form = HtmlForm::parse(
+            create_page
+                .select(create_folder_form_selector)
+                .next()
+                .whatever_context("Did not find folder creation form")?,
+        )?;
+        form.set("title", title);
+        form.set("desc", description);
+
+        let response = form
+            .submit(ilias_client, "save")
+            .whatever_context(format!("Could not create folder {title}"))?;
+        info!("Created folder {title} in {}", self.name);
+
+        // ILIAS usually redirects to the new folder, otherwise look for it in this folder
+        let response_page = Html::parse_document(&ilias_client.get_text(response)?);
+        if let Ok(folder) = Folder::parse(response_page.root_element(), ilias_client)
+            && folder.name == title
+        {
+            return Ok(folder);
+        }
+
+        let parent_page = ilias_client.get_querypath(&self.querypath)?;
+        let parent = Folder::parse(parent_page.root_element(), ilias_client)?;
+        parent
+            .sub_folder(ilias_client, title)?
+            .whatever_context(format!("Could not find created folder {title}"))
+    }
+
+    /// Parse the sub-container with the given name, if there is one
+    pub fn sub_folder(
+        &self,
+        ilias_client: &IliasClient,
+        name: &str,
+    ) -> Result<Option<Folder>, Whatever> {
+        let Some(querypath) = self.elements.iter().find_map(|element| match element {
+            FolderElement::Viewable {
+                name: element_name,
+                querypath,
+                ..
+            } if element_name.trim() == name => Some(querypath),
+            _ => None,
+        }) else {
+            return Ok(None);
+        };
+
+        let page = ilias_client.get_querypath(querypath)?;
+        Folder::parse(page.root_element(), ilias_client).map(Some)
+    }
+
+    /// Parse this folder again, e.g. to see the elements added by an upload
+    pub(crate) fn reload(&self, ilias_client: &IliasClient) -> Result<Folder, Whatever> {
+        let page = ilias_client
+            .get_querypath(&self.querypath)
+            .whatever_context(format!("Could not get folder {}", self.name))?;
+        Folder::parse(page.root_element(), ilias_client)
+    }
+
+    /// Upload files and return them as listed in the folder afterwards
     pub fn upload_files(
         &self,
         ilias_client: &IliasClient,
         files: &[NamedLocalFile],
-    ) -> Result<(), Whatever> {
-        debug!(
-            "Uploading files: {:?} to {:?}",
-            files, &self.upload_page_querypath
-        );
+    ) -> Result<Vec<File>, Whatever> {
+        self.upload_files_with_options(ilias_client, files, &UploadOptions::default())
+    }
+
+    /// The licenses that can be chosen for uploaded files via [`UploadOptions::license`]
+    pub fn upload_license_options(
+        &self,
+        ilias_client: &IliasClient,
+    ) -> Result<Vec<SelectOption>, Whatever> {
+        let (_, upload_form) = self.upload_form(ilias_client)?;
+        Ok(upload_form
+            .select_options(license_field_name(&upload_form))
+            .to_vec())
+    }
+
+    fn upload_form(&self, ilias_client: &IliasClient) -> Result<(Html, HtmlForm), Whatever> {
         let upload_page = ilias_client.get_querypath(
             &self
                 .upload_page_querypath
@@ -177,51 +453,105 @@ impl Folder {
         let upload_form_selector = CONTENT_FORM_SELECTOR.get_or_init(|| {
             Selector::parse("#ilContentContainer form").expect("Could not parse scraper")
         });
-        let script_tag_selector = SCRIPT_TAG_SELECTOR.get_or_init(|| {
-            Selector::parse("body script:not([src])").expect("Could not parse scraper")
-        });
 
-        let finish_upload_querypath = upload_page
-            .select(upload_form_selector)
-            .next()
-            .unwrap()
-            .value()
-            .attr("action")
-            .unwrap();
+        let upload_form = HtmlForm::parse(
+            upload_page
+                .select(upload_form_selector)
+                .next()
+                .whatever_context("Could not find upload form")?,
+        )?;
+        Ok((upload_page, upload_form))
+    }
+
+    /// Upload files, applying the description, license and availability of `options` to each
+    /// of them. Returns the uploaded files as listed in the folder afterwards and fails if they
+    /// differ from `files`.
+    pub fn upload_files_with_options(
+        &self,
+        ilias_client: &IliasClient,
+        files: &[NamedLocalFile],
+        options: &UploadOptions,
+    ) -> Result<Vec<File>, Whatever> {
+        let known_ids = self.current_ids(ilias_client)?;
+        self.upload_new_files(ilias_client, files, options, &known_ids)
+    }
+
+    /// Upload files and verify them against the elements that are not in `known_ids` afterwards
+    fn upload_new_files(
+        &self,
+        ilias_client: &IliasClient,
+        files: &[NamedLocalFile],
+        options: &UploadOptions,
+        known_ids: &[RefId],
+    ) -> Result<Vec<File>, Whatever> {
+        self.submit_uploads(ilias_client, files, options, None)?;
+
+        let listed = self
+            .new_elements(ilias_client, known_ids)?
+            .into_iter()
+            .filter_map(|element| match element {
+                FolderElement::File { file, .. } => Some(file),
+                _ => None,
+            })
+            .collect();
+        verify_uploads(files, listed)
+            .whatever_context(format!("Could not verify upload to {}", self.name))
+    }
+
+    /// Upload files through the upload form, optionally letting ILIAS unpack zip archives
+    fn submit_uploads(
+        &self,
+        ilias_client: &IliasClient,
+        files: &[NamedLocalFile],
+        options: &UploadOptions,
+        unzip: Option<UnzipMode>,
+    ) -> Result<(), Whatever> {
+        debug!(
+            "Uploading files: {:?} to {:?} with {:?}",
+            files, &self.upload_page_querypath, options
+        );
+        let (upload_page, upload_form) = self.upload_form(ilias_client)?;
+
+        let finish_upload_querypath = &upload_form.action;
         debug!("Finish upload querypath: {}", finish_upload_querypath);
 
-        let relevant_script_tag = upload_page
-            .select(script_tag_selector)
-            .next()
-            .unwrap()
-            .text()
-            .collect::<String>();
+        let license_field = license_field_name(&upload_form);
+        let (unzip_field, keep_structure_field) = unzip_field_names(&upload_form);
+        let license = match &options.license {
+            None => "7".to_string(), // All rights reserved
+            Some(license) => upload_form
+                .select_options(license_field)
+                .iter()
+                .find(|option| option.value == *license || option.label == *license)
+                .whatever_context(format!("License {license} is not available"))?
+                .value
+                .clone(),
+        };
+
+        let property_fields =
+            upload_property_fields(&upload_form, options, ilias_client.timezone())?;
 
-        let path_regex =
-            Regex::new(r".*il\.UI\.Input\.File\.init\([^']*'[^']*',[^']*'(?<querypath>[^']+)'.*")
-                .whatever_context("Could not parse cursed regex lol")?;
-        let upload_querypath = &path_regex
-            .captures(&relevant_script_tag)
-            .whatever_context("No match for upload querypath found :(")?["querypath"];
+        let upload_querypath = ilias_client.file_input_upload_querypath(&upload_page)?;
         debug!("Upload querypath: {}", upload_querypath);
 
         for file_data in files {
-            let form = Form::new().part(
-                "file[0]",
-                ilias_client.construct_file_part(&file_data.path)?,
-            );
+            let file_id = ilias_client.upload_to_file_input(&upload_querypath, &file_data.path)?;
 
-            let response = ilias_client.post_querypath_multipart(upload_querypath, form)?;
-            let response: IliasUploadResponse = ilias_client.get_json(response)?;
-            debug!("Upload response: {response:?}");
-            let file_id = response.file_id;
-
-            let finish_form = Form::new()
+            let mut finish_form = Form::new()
                 .text("form/input_0[input_1][]", file_data.name.clone()) // Filename
-                .text("form/input_0[input_2][]", "") // Description
-                .text("form/input_0[input_3][]", file_id) // File id
-                .text("form/input_1", "7") // License: All rights reserved
-                .percent_encode_noop();
+                .text("form/input_0[input_2][]", options.description.clone()) // Description
+                .text("form/input_0[input_3][]", file_id); // File id
+            if let Some(unzip) = unzip {
+                finish_form = finish_form.text(unzip_field.to_string(), "checked");
+                if unzip == UnzipMode::KeepStructure {
+                    finish_form = finish_form.text(keep_structure_field.to_string(), "checked");
+                }
+            }
+            finish_form = finish_form.text(license_field.to_string(), license.clone());
+            for (name, value) in &property_fields {
+                finish_form = finish_form.text(name.clone(), value.clone());
+            }
+            let finish_form = finish_form.percent_encode_noop();
 
             let response =
                 ilias_client.post_querypath_multipart(finish_upload_querypath, finish_form)?;
@@ -237,7 +567,53 @@ impl Folder {
         }
 
         Ok(())
-        // TODO: Maybe push files to submission here
+    }
+
+    /// The ids of the elements in the folder right now, which `self.elements` may be outdated on.
+    /// Unlike [`Folder::reload`] this does not fetch the actions of every element.
+    pub(crate) fn current_ids(&self, ilias_client: &IliasClient) -> Result<Vec<RefId>, Whatever> {
+        let element_selector = ELEMENT_SELECTOR
+            .get_or_init(|| Selector::parse(".ilObjListRow").expect("Could not parse selector"));
+
+        let page = ilias_client
+            .get_querypath(&self.querypath)
+            .whatever_context(format!("Could not get folder {}", self.name))?;
+        Ok(page
+            .select(element_selector)
+            .filter_map(|row| FolderElement::row_id(row))
+            .collect())
+    }
+
+    /// The elements of the reloaded folder whose ids are not in `known_ids`. Only these are
+    /// parsed, as parsing an element fetches its actions.
+    pub(crate) fn new_elements(
+        &self,
+        ilias_client: &IliasClient,
+        known_ids: &[RefId],
+    ) -> Result<Vec<FolderElement>, Whatever> {
+        let element_selector = ELEMENT_SELECTOR
+            .get_or_init(|| Selector::parse(".ilObjListRow").expect("Could not parse selector"));
+        let last_script_selector = LAST_SCRIPT_SELECTOR.get_or_init(|| {
+            Selector::parse("body script:last-child").expect("Could not parse selector")
+        });
+
+        let page = ilias_client
+            .get_querypath(&self.querypath)
+            .whatever_context(format!("Could not get folder {}", self.name))?;
+        let last_script = page
+            .select(last_script_selector)
+            .next()
+            .whatever_context("Did not find last script")?
+            .text()
+            .collect::<String>();
+
+        page.select(element_selector)
+            .filter(|&row| FolderElement::row_id(row).is_some_and(|id| !known_ids.contains(&id)))
+            .map(|row| {
+                FolderElement::parse(row, &last_script, ilias_client)
+                    .whatever_context("Could not parse new folder element")
+            })
+            .collect()
     }
 }
 
@@ -246,6 +622,9 @@ static ELEMENT_DESCRIPTION_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static ELEMENT_ACTIONS_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static ELEMENT_PROPERTY_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
+static ROW_ID_REGEX: OnceLock<Regex> = OnceLock::new();
+static OBJECT_TYPE_REGEX: OnceLock<Regex> = OnceLock::new();
+
 impl FolderElement {
     fn parse(
         element: ElementRef,
@@ -275,43 +654,83 @@ impl FolderElement {
             .attr("href")
             .whatever_context("Could not get link")?;
         let description = description_element.text().collect();
-        let querypath = Url::parse(link)
-            .expect("Could not parse link")
-            .get_querypath();
-
-        let id = Regex::new(r"(ref_id=|target=file_|exc/)(?<id>\d+)")
-            .whatever_context("Could not parse regex")?
-            .captures(&querypath)
-            .and_then(|capture| capture.name("id"))
-            .whatever_context(format!(
-                "Could not get id captures from querypath {querypath}"
-            ))?
-            .as_str()
-            .to_string();
+        let link_url = Url::parse(link).whatever_context("Could not parse link")?;
+        let is_external = link_url.host_str() != ilias_client.base_url().host_str();
+        let querypath = Querypath::parse(&link_url.get_querypath());
+
+        let id = Self::row_id(element);
+        if id.is_none() {
+            debug!("Could not get id for element {name} with querypath {querypath}");
+        }
+
+        let target_url = is_external.then(|| link.to_string());
 
-        let deletion_querypath = Self::get_deletion_querypath(&id, folder_script, ilias_client);
+        let deletion_querypath = id
+            .as_ref()
+            .and_then(|id| Self::get_deletion_querypath(id, folder_script, ilias_client));
 
         Self::extract_from_querypath(
             querypath,
             name,
             description,
             id,
+            target_url,
             deletion_querypath,
             &mut properties,
+            ilias_client.timezone(),
         )
     }
 
+    /// The ref id of the element in `row`, from its link or else from the ids of its actions
+    fn row_id(row: ElementRef) -> Option<RefId> {
+        let element_name_selector = ELEMENT_NAME_SELECTOR.get_or_init(|| {
+            Selector::parse(".il_ContainerItemTitle a").expect("Could not parse selector")
+        });
+        let row_id_regex = ROW_ID_REGEX.get_or_init(|| {
+            Regex::new(r"(_act_|lg_div_)(?<id>\d+)_pref_").expect("Could not parse regex")
+        });
+
+        row.select(element_name_selector)
+            .next()
+            .and_then(|link| link.attr("href"))
+            .and_then(|link| Url::parse(link).ok())
+            .and_then(|url| Querypath::parse(&url.get_querypath()).ref_id())
+            .or_else(|| {
+                row_id_regex
+                    .captures(&row.html())
+                    .map(|captures| RefId::new(&captures["id"]))
+            })
+    }
+
+    fn object_type(querypath: &str) -> Option<&str> {
+        let object_type_regex = OBJECT_TYPE_REGEX.get_or_init(|| {
+            Regex::new(r"(goto\.php/|target=)(?<type>[a-z]+)[/_]\d+")
+                .expect("Could not parse regex")
+        });
+        object_type_regex
+            .captures(querypath)
+            .and_then(|captures| captures.name("type"))
+            .map(|object_type| object_type.as_str())
+    }
+
+    fn is_web_link(querypath: &str) -> bool {
+        Self::object_type(querypath) == Some("webr")
+            || querypath
+                .to_lowercase()
+                .contains("baseclass=illinkresourcehandlergui")
+    }
+
     fn get_deletion_querypath(
-        id: &str,
+        id: &RefId,
         folder_script: &str,
         ilias_client: &IliasClient,
-    ) -> Option<String> {
+    ) -> Option<Querypath> {
         let element_actions_selector = ELEMENT_ACTIONS_SELECTOR
             .get_or_init(|| Selector::parse("li>a").expect("Could not parse selector"));
 
         let regex = format!(
             r##"\$\("#ilAdvSelListAnchorText_act_{}_pref_\d+"\).click\((?:.|\n)*ajaxReplaceInner\('(?<querypath>[^']+)', 'ilAdvSelListTable_act_{}"##,
-            &id, &id
+            id, id
         );
         let actions_querypath = Regex::new(&regex)
             .ok()?
@@ -325,37 +744,71 @@ impl FolderElement {
             .select(element_actions_selector)
             .filter_map(|element| element.attr("href"))
             .find(|&href| href.contains("cmd=delete"))
-            .map(ToOwned::to_owned)
+            .map(Querypath::parse)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn extract_from_querypath(
-        querypath: String,
+        querypath: Querypath,
         name: String,
         description: String,
-        id: String,
-        deletion_querypath: Option<String>,
+        id: Option<RefId>,
+        target_url: Option<String>,
+        deletion_querypath: Option<Querypath>,
         properties: &mut Select<'_, '_>,
+        timezone: Tz,
     ) -> Result<FolderElement, Whatever> {
         debug!("Querypath: {}", querypath);
-        if querypath.contains("target=file_")
+        let Some(id) = id else {
+            return Ok(FolderElement::Unknown {
+                name,
+                description,
+                id: None,
+                querypath,
+                deletion_querypath,
+            });
+        };
+
+        let object_type = Self::object_type(&querypath);
+        let lowercase_querypath = querypath.to_lowercase();
+        let is_object = |types: &[&str], base_classes: &[&str]| {
+            object_type.is_some_and(|object_type| types.contains(&object_type))
+                || base_classes
+                    .iter()
+                    .any(|base_class| lowercase_querypath.contains(base_class))
+        };
+
+        if target_url.is_some() || Self::is_web_link(&querypath) {
+            Ok(FolderElement::WebLink {
+                name,
+                description,
+                id,
+                querypath,
+                target_url,
+                deletion_querypath,
+            })
+        } else if querypath.contains("target=file_")
             || (querypath.contains("baseClass=ilrepositorygui")
                 && querypath.contains("cmd=sendfile"))
         {
             let extension: String = properties
                 .next()
-                .expect("Could not find file extension")
+                .whatever_context("Could not find file extension")?
                 .text()
                 .collect::<String>()
                 .trim()
                 .to_string();
+            // The size is shown between the extension and the date
+            let mut size = None;
             let date = loop {
                 let next_property = properties
                     .next()
-                    .whatever_context("No date properties left")?;
-                let date = parse_date(&next_property.text().collect::<String>());
-                match date {
+                    .whatever_context("No date properties left")?
+                    .text()
+                    .collect::<String>();
+                match parse_date(&next_property, timezone) {
                     Ok(date) => break Some(date),
-                    Err(_) => continue,
+                    Err(_) => size = size.or(parse_file_size(&next_property)),
                 }
             };
 
@@ -369,8 +822,9 @@ impl FolderElement {
                 name,
                 description,
                 date,
-                id: Some(id.to_string()),
+                id: Some(FileId::Ref(id)),
                 download_querypath: Some(querypath),
+                size,
             };
 
             Ok(FolderElement::File {
@@ -390,11 +844,9 @@ impl FolderElement {
             })
         } else if querypath.contains("baseClass=ilrepositorygui") && querypath.contains("cmd=view")
         {
-            let id = Regex::new(r"ref_id=(?<id>\d+)")
-                .whatever_context("Could not parse regex")?
-                .captures(&querypath)
-                .whatever_context("Could not extract id")?["id"]
-                .to_string();
+            let id = querypath
+                .ref_id()
+                .whatever_context("Could not extract id")?;
             Ok(FolderElement::Viewable {
                 name,
                 description,
@@ -402,6 +854,17 @@ impl FolderElement {
                 querypath,
                 deletion_querypath,
             })
+        } else if querypath.contains("/frm/")
+            || querypath.contains("target=frm_")
+            || querypath.contains("cmd=showThreads")
+        {
+            Ok(FolderElement::Forum {
+                name,
+                description,
+                id,
+                querypath,
+                deletion_querypath,
+            })
         } else if querypath.contains("/exc/") {
             Ok(FolderElement::Exercise {
                 name,
@@ -410,12 +873,74 @@ impl FolderElement {
                 querypath,
                 deletion_querypath,
             })
+        } else if is_object(&["tst"], &["ilobjtestgui", "iltestplayer"]) {
+            Ok(FolderElement::Test {
+                name,
+                description,
+                id,
+                querypath,
+                deletion_querypath,
+            })
+        } else if is_object(&["wiki"], &["ilwikihandlergui"]) {
+            Ok(FolderElement::Wiki {
+                name,
+                description,
+                id,
+                querypath,
+                deletion_querypath,
+            })
+        } else if is_object(
+            &["lm", "htlm", "sahs"],
+            &[
+                "illmpresentationgui",
+                "ilhtlmpresentationgui",
+                "ilsahspresentationgui",
+            ],
+        ) {
+            Ok(FolderElement::LearningModule {
+                name,
+                description,
+                id,
+                querypath,
+                deletion_querypath,
+            })
+        } else if is_object(&["sess"], &["ilobjsessiongui"]) {
+            Ok(FolderElement::Session {
+                name,
+                description,
+                id,
+                querypath,
+                deletion_querypath,
+            })
+        } else if is_object(&["svy"], &["ilobjsurveygui"]) {
+            Ok(FolderElement::Survey {
+                name,
+                description,
+                id,
+                querypath,
+                deletion_querypath,
+            })
+        } else if is_object(&["poll"], &["ilobjpollgui"]) {
+            Ok(FolderElement::Poll {
+                name,
+                description,
+                id,
+                querypath,
+                deletion_querypath,
+            })
         } else {
-            whatever!("Could not get folder element")
+            debug!("Unknown folder element {name} with querypath {querypath}");
+            Ok(FolderElement::Unknown {
+                name,
+                description,
+                id: Some(id),
+                querypath,
+                deletion_querypath,
+            })
         }
     }
 
-    fn deletion_querypath(&self) -> Option<&String> {
+    pub fn deletion_querypath(&self) -> Option<&Querypath> {
         match self {
             Self::File {
                 deletion_querypath, ..
@@ -423,16 +948,61 @@ impl FolderElement {
             | Self::Exercise {
                 deletion_querypath, ..
             }
+            | Self::Forum {
+                deletion_querypath, ..
+            }
             | Self::Opencast {
                 deletion_querypath, ..
             }
             | Self::Viewable {
                 deletion_querypath, ..
+            }
+            | Self::WebLink {
+                deletion_querypath, ..
+            }
+            | Self::Test {
+                deletion_querypath, ..
+            }
+            | Self::Wiki {
+                deletion_querypath, ..
+            }
+            | Self::LearningModule {
+                deletion_querypath, ..
+            }
+            | Self::Session {
+                deletion_querypath, ..
+            }
+            | Self::Survey {
+                deletion_querypath, ..
+            }
+            | Self::Poll {
+                deletion_querypath, ..
+            }
+            | Self::Unknown {
+                deletion_querypath, ..
             } => deletion_querypath,
         }
         .as_ref()
     }
 
+    pub fn kind(&self) -> ElementKind {
+        match self {
+            Self::File { .. } => ElementKind::File,
+            Self::Exercise { .. } => ElementKind::Exercise,
+            Self::Forum { .. } => ElementKind::Forum,
+            Self::Opencast { .. } => ElementKind::Opencast,
+            Self::Viewable { .. } => ElementKind::Viewable,
+            Self::WebLink { .. } => ElementKind::WebLink,
+            Self::Test { .. } => ElementKind::Test,
+            Self::Wiki { .. } => ElementKind::Wiki,
+            Self::LearningModule { .. } => ElementKind::LearningModule,
+            Self::Session { .. } => ElementKind::Session,
+            Self::Survey { .. } => ElementKind::Survey,
+            Self::Poll { .. } => ElementKind::Poll,
+            Self::Unknown { .. } => ElementKind::Unknown,
+        }
+    }
+
     pub fn file(&self) -> Option<&File> {
         match self {
             Self::File {
@@ -443,19 +1013,99 @@ impl FolderElement {
         }
     }
 
-    fn id(&self) -> &str {
+    /// Upload `file` as a new version of a file element, keeping its id and version history
+    pub fn upload_new_version(
+        &self,
+        ilias_client: &IliasClient,
+        file: &NamedLocalFile,
+        mode: VersionMode,
+    ) -> Result<(), Whatever> {
+        let Some(remote_file) = self.file() else {
+            whatever!("{self} is not a file");
+        };
+        remote_file.upload_new_version(ilias_client, file, mode)
+    }
+
+    /// Fetch the episodes of an Opencast element
+    pub fn opencast_series(&self, ilias_client: &IliasClient) -> Result<OpencastSeries, Whatever> {
+        let Self::Opencast { querypath, .. } = self else {
+            whatever!("{self} is not an Opencast series");
+        };
+        let series_page = ilias_client
+            .get_querypath(querypath)
+            .whatever_context("Could not get Opencast series page")?;
+        OpencastSeries::parse(series_page.root_element(), ilias_client)
+    }
+
+    /// Fetch the threads of a forum element
+    pub fn forum(&self, ilias_client: &IliasClient) -> Result<Forum, Whatever> {
+        let Self::Forum { querypath, .. } = self else {
+            whatever!("{self} is not a forum");
+        };
+        let forum_page = ilias_client
+            .get_querypath(querypath)
+            .whatever_context("Could not get forum page")?;
+        Forum::parse(forum_page.root_element(), ilias_client)
+    }
+
+    /// The external url of a web link element. Unless the folder links to it directly, this
+    /// follows the redirects of ILIAS.
+    pub fn web_link_target(&self, ilias_client: &IliasClient) -> Result<String, Whatever> {
+        let Self::WebLink {
+            name,
+            querypath,
+            target_url,
+            ..
+        } = self
+        else {
+            whatever!("{self} is not a web link");
+        };
+        if let Some(target_url) = target_url {
+            return Ok(target_url.clone());
+        }
+
+        let target = ilias_client
+            .get_redirect_target(querypath)
+            .whatever_context(format!("Could not resolve web link {name}"))?;
+        if target.host_str() == ilias_client.base_url().host_str() {
+            whatever!("Web link {name} does not lead out of ILIAS");
+        }
+        Ok(target.to_string())
+    }
+
+    pub fn id(&self) -> Option<RefId> {
         match self {
-            Self::File { file, .. } => file.id.as_ref().unwrap(),
-            Self::Exercise { id, .. } | Self::Opencast { id, .. } | Self::Viewable { id, .. } => id,
+            Self::File { file, .. } => file.id.as_ref().and_then(FileId::ref_id).cloned(),
+            Self::Exercise { id, .. }
+            | Self::Forum { id, .. }
+            | Self::Opencast { id, .. }
+            | Self::Viewable { id, .. }
+            | Self::WebLink { id, .. }
+            | Self::Test { id, .. }
+            | Self::Wiki { id, .. }
+            | Self::LearningModule { id, .. }
+            | Self::Session { id, .. }
+            | Self::Survey { id, .. }
+            | Self::Poll { id, .. } => Some(id.clone()),
+            Self::Unknown { id, .. } => id.clone(),
         }
     }
 
-    fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         match self {
             Self::File { file, .. } => &file.name,
             Self::Exercise { name, .. }
+            | Self::Forum { name, .. }
             | Self::Opencast { name, .. }
-            | Self::Viewable { name, .. } => name,
+            | Self::Viewable { name, .. }
+            | Self::WebLink { name, .. }
+            | Self::Test { name, .. }
+            | Self::Wiki { name, .. }
+            | Self::LearningModule { name, .. }
+            | Self::Session { name, .. }
+            | Self::Survey { name, .. }
+            | Self::Poll { name, .. }
+            | Self::Unknown { name, .. } => name,
         }
     }
 
@@ -480,7 +1130,10 @@ impl FolderElement {
             .whatever_context("Could not find action on form")?;
         debug!("Delete confirm querypath: {}", confirm_querypath);
 
-        let form_data = [("form/input_0", self.id())];
+        let id = self
+            .id()
+            .whatever_context(format!("Element {} has no id", self.name()))?;
+        let form_data = [("form/input_0", id.as_str())];
 
         ilias_client
             .post_querypath_form(confirm_querypath, &form_data)
@@ -489,8 +1142,7 @@ impl FolderElement {
             ))?;
         info!(
             "Deleted {} via deletion querypath {:?}",
-            self.id(),
-            deletion_querypath
+            id, deletion_querypath
         );
         Ok(())
     }
@@ -510,6 +1162,13 @@ impl Display for FolderElement {
                 querypath: _,
                 deletion_querypath: _,
             } => write!(f, "Exercise {name}"),
+            FolderElement::Forum {
+                name,
+                description: _,
+                id: _,
+                querypath: _,
+                deletion_querypath: _,
+            } => write!(f, "Forum {name}"),
             FolderElement::Opencast {
                 name,
                 description: _,
@@ -524,6 +1183,19 @@ impl Display for FolderElement {
                 querypath: _,
                 deletion_querypath: _,
             } => write!(f, "Folder(-like) {name}"),
+            FolderElement::WebLink {
+                name, target_url, ..
+            } => match target_url {
+                Some(target_url) => write!(f, "Web link {name} ({target_url})"),
+                None => write!(f, "Web link {name}"),
+            },
+            FolderElement::Test { name, .. } => write!(f, "Test {name}"),
+            FolderElement::Wiki { name, .. } => write!(f, "Wiki {name}"),
+            FolderElement::LearningModule { name, .. } => write!(f, "Learning module {name}"),
+            FolderElement::Session { name, .. } => write!(f, "Session {name}"),
+            FolderElement::Survey { name, .. } => write!(f, "Survey {name}"),
+            FolderElement::Poll { name, .. } => write!(f, "Poll {name}"),
+            FolderElement::Unknown { name, .. } => write!(f, "Unknown element {name}"),
         }
     }
 }
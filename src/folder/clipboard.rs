@@ -0,0 +1,216 @@
+use std::sync::OnceLock;
+
+use log::{debug, info};
+use scraper::{Html, Selector};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use crate::{client::IliasClient, form::HtmlForm, id::RefId};
+
+use super::{Folder, FolderElement};
+
+static PASTE_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static COPY_TARGET_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static COPY_WIZARD_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+impl Folder {
+    /// Move elements of this folder into `target` via cut and paste. Returns the moved elements
+    /// as they appear in `target`, they keep their ids.
+    pub fn move_elements(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&FolderElement],
+        target: &Folder,
+    ) -> Result<Vec<FolderElement>, Whatever> {
+        let ids = element_ids(elements)?;
+        let paste_page = self.post_clipboard_command(ilias_client, "cut", &ids)?;
+        self.paste(ilias_client, &paste_page, target)?;
+        info!(
+            "Moved {} elements from {} to {}",
+            ids.len(),
+            self.name,
+            target.name
+        );
+
+        let moved = target
+            .reload(ilias_client)?
+            .elements
+            .into_iter()
+            .filter(|element| element.id().is_some_and(|id| ids.contains(&id)))
+            .collect();
+        Ok(moved)
+    }
+
+    /// Link elements of this folder into `target`, so they show up in both containers. Returns
+    /// the links in `target`, which have new ids.
+    pub fn link_elements(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&FolderElement],
+        target: &Folder,
+    ) -> Result<Vec<FolderElement>, Whatever> {
+        let ids = element_ids(elements)?;
+        let known_ids = target.current_ids(ilias_client)?;
+        let paste_page = self.post_clipboard_command(ilias_client, "link", &ids)?;
+        self.paste(ilias_client, &paste_page, target)?;
+        info!(
+            "Linked {} elements from {} to {}",
+            ids.len(),
+            self.name,
+            target.name
+        );
+
+        target.new_elements_named(ilias_client, &known_ids, elements)
+    }
+
+    /// Copy elements of this folder into `target`. Returns the copies in `target`.
+    pub fn copy_elements(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&FolderElement],
+        target: &Folder,
+    ) -> Result<Vec<FolderElement>, Whatever> {
+        let copy_target_form_selector = COPY_TARGET_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"form:has([name="cmd[saveTarget]"])"#)
+                .expect("Could not parse selector")
+        });
+        let copy_wizard_form_selector = COPY_WIZARD_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"form:has([name="cmd[copyContainer]"])"#)
+                .expect("Could not parse selector")
+        });
+
+        let ids = element_ids(elements)?;
+        let target_id = target
+            .ref_id()
+            .whatever_context("Target folder has no id")?;
+        let known_ids = target.current_ids(ilias_client)?;
+        let target_page = self.post_clipboard_command(ilias_client, "copy", &ids)?;
+
+        let mut target_form = HtmlForm::parse(
+            target_page
+                .select(copy_target_form_selector)
+                .next()
+                .whatever_context("Could not find copy target selection")?,
+        )?;
+        target_form.set("target", target_id.as_str());
+        let response = target_form
+            .submit(ilias_client, "saveTarget")
+            .whatever_context("Could not select copy target")?;
+
+        // Copying containers asks what to do with their contents, the defaults copy everything
+        let response_page = Html::parse_document(&ilias_client.get_text(response)?);
+        if let Some(wizard_form) = response_page.select(copy_wizard_form_selector).next() {
+            debug!("Confirming copy wizard");
+            HtmlForm::parse(wizard_form)?
+                .submit(ilias_client, "copyContainer")
+                .whatever_context("Could not confirm copy wizard")?;
+        }
+        info!(
+            "Copied {} elements from {} to {}",
+            ids.len(),
+            self.name,
+            target.name
+        );
+
+        target.new_elements_named(ilias_client, &known_ids, elements)
+    }
+
+    /// Send `command` for the elements with the given ids as if they were selected in the
+    /// container view
+    pub(super) fn post_clipboard_command(
+        &self,
+        ilias_client: &IliasClient,
+        command: &str,
+        ids: &[RefId],
+    ) -> Result<Html, Whatever> {
+        let ref_id = self.ref_id().whatever_context("Folder has no id")?;
+        let mut form_data: Vec<(String, String)> = ids
+            .iter()
+            .map(|id| ("id[]".to_string(), id.to_string()))
+            .collect();
+        form_data.push((format!("cmd[{command}]"), command.to_string()));
+
+        let response = ilias_client
+            .post_querypath_form(
+                &format!("ilias.php?baseClass=ilrepositorygui&ref_id={ref_id}&cmd=post"),
+                &form_data,
+            )
+            .whatever_context(format!("Could not {command} elements of {}", self.name))?;
+        Ok(Html::parse_document(&ilias_client.get_text(response)?))
+    }
+
+    /// Paste the clipboard into `target`, using the paste form ILIAS shows after cut or link
+    fn paste(
+        &self,
+        ilias_client: &IliasClient,
+        paste_page: &Html,
+        target: &Folder,
+    ) -> Result<(), Whatever> {
+        let paste_form_selector = PASTE_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"form:has([name="cmd[performPasteIntoMultipleObjects]"])"#)
+                .expect("Could not parse selector")
+        });
+
+        let target_id = target
+            .ref_id()
+            .whatever_context("Target folder has no id")?;
+        let mut paste_form = match paste_page.select(paste_form_selector).next() {
+            Some(paste_form) => HtmlForm::parse(paste_form)?,
+            None => {
+                let ref_id = self.ref_id().whatever_context("Folder has no id")?;
+                let paste_querypath = format!(
+                    "ilias.php?baseClass=ilrepositorygui&ref_id={ref_id}&cmd=showPasteTree"
+                );
+                let paste_page = ilias_client.get_querypath(&paste_querypath)?;
+                HtmlForm::parse(
+                    paste_page
+                        .select(paste_form_selector)
+                        .next()
+                        .whatever_context("Could not find paste form")?,
+                )?
+            }
+        };
+        paste_form.set("nodes[]", target_id.as_str());
+
+        let response = paste_form
+            .submit(ilias_client, "performPasteIntoMultipleObjects")
+            .whatever_context(format!("Could not paste into {}", target.name))?;
+        if ilias_client
+            .is_alert_response(response)
+            .whatever_context("Could not check error state of response")?
+        {
+            whatever!("Pasting into {} failed", target.name)
+        }
+        Ok(())
+    }
+
+    /// Elements of the reloaded folder whose ids are not in `known_ids` and that are named like
+    /// one of `elements`
+    fn new_elements_named(
+        &self,
+        ilias_client: &IliasClient,
+        known_ids: &[RefId],
+        elements: &[&FolderElement],
+    ) -> Result<Vec<FolderElement>, Whatever> {
+        let new_elements = self
+            .new_elements(ilias_client, known_ids)?
+            .into_iter()
+            .filter(|element| {
+                elements
+                    .iter()
+                    .any(|original| original.name() == element.name())
+            })
+            .collect();
+        Ok(new_elements)
+    }
+}
+
+fn element_ids(elements: &[&FolderElement]) -> Result<Vec<RefId>, Whatever> {
+    elements
+        .iter()
+        .map(|element| {
+            element
+                .id()
+                .whatever_context(format!("Element {element} has no id"))
+        })
+        .collect()
+}
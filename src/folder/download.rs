@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use log::info;
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use crate::client::{
+    IliasClient,
+    background_task::{BackgroundTaskState, PollOptions},
+};
+
+use super::{Folder, FolderElement};
+
+impl Folder {
+    /// Download all elements of this folder as a single zip archive
+    pub fn download_zip(
+        &self,
+        ilias_client: &IliasClient,
+        to: &Path,
+        poll_options: &PollOptions,
+    ) -> Result<(), Whatever> {
+        let elements: Vec<&FolderElement> = self.elements.iter().collect();
+        self.download_elements_zip(ilias_client, &elements, to, poll_options)
+    }
+
+    /// Download the given elements of this folder as a single zip archive. ILIAS packages the
+    /// archive in a background task, this waits until the task is done.
+    pub fn download_elements_zip(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&FolderElement],
+        to: &Path,
+        poll_options: &PollOptions,
+    ) -> Result<(), Whatever> {
+        let ids = elements
+            .iter()
+            .map(|element| {
+                element
+                    .id()
+                    .whatever_context(format!("Element {element} has no id"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if ids.is_empty() {
+            whatever!("There is nothing to download in {}", self.name)
+        }
+        let ref_id = self.ref_id().whatever_context("Folder has no id")?;
+
+        // Earlier downloads of this folder may still be listed
+        let known_tasks = ilias_client.background_tasks()?;
+        let page = self.post_clipboard_command(ilias_client, "download", &ids)?;
+        let task = ilias_client
+            .wait_for_background_task(
+                Some(&page),
+                &known_tasks,
+                |task| task.was_started_with_param("ref_id", ref_id.as_str()),
+                poll_options,
+            )
+            .whatever_context(format!("Could not get zip of {}", self.name))?;
+        if task.state == BackgroundTaskState::Failed {
+            whatever!("ILIAS failed to create the zip of {}", self.name)
+        }
+
+        info!("Downloading zip of {} to {to:?}", self.name);
+        task.download(ilias_client, to)
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Local};
+use log::info;
+use scraper::Selector;
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use crate::{client::IliasClient, form::HtmlForm};
+
+use super::{Folder, FolderElement};
+
+/// Changes to the properties of a repository element. Properties that are `None` are left as
+/// they are.
+#[derive(Clone, Debug, Default)]
+pub struct ElementProperties {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub online: Option<bool>,
+    /// Only make the element available to members during this period
+    pub availability: Option<AvailabilityPeriod>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AvailabilityPeriod {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// Whether members can see (but not open) the element outside of the period
+    pub visible_outside: bool,
+}
+
+/// Format of the date inputs of legacy ILIAS forms
+pub(super) const FORM_DATE_FORMAT: &str = "%d.%m.%Y %H:%M";
+
+static PROPERTIES_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+impl Folder {
+    /// Rename an element of this folder and return the renamed element
+    pub fn rename_element(
+        &self,
+        ilias_client: &IliasClient,
+        element: &FolderElement,
+        title: &str,
+    ) -> Result<FolderElement, Whatever> {
+        let properties = ElementProperties {
+            title: Some(title.to_string()),
+            ..Default::default()
+        };
+        self.edit_element(ilias_client, element, &properties)
+    }
+
+    /// Change the properties of an element of this folder and return the updated element
+    pub fn edit_element(
+        &self,
+        ilias_client: &IliasClient,
+        element: &FolderElement,
+        properties: &ElementProperties,
+    ) -> Result<FolderElement, Whatever> {
+        element.update_properties(ilias_client, properties)?;
+
+        let id = element.id().whatever_context("Element has no id")?;
+        self.reload(ilias_client)?
+            .elements
+            .into_iter()
+            .find(|element| element.id().as_ref() == Some(&id))
+            .whatever_context(format!("Could not find {element} after editing it"))
+    }
+}
+
+impl FolderElement {
+    /// Submit the properties form ("Einstellungen") of this element with the given changes
+    pub(super) fn update_properties(
+        &self,
+        ilias_client: &IliasClient,
+        properties: &ElementProperties,
+    ) -> Result<(), Whatever> {
+        let properties_form_selector = PROPERTIES_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse("#ilContentContainer form").expect("Could not parse selector")
+        });
+
+        let id = self
+            .id()
+            .whatever_context(format!("Element {self} has no id"))?;
+        let properties_page = ilias_client.get_querypath(&format!(
+            "ilias.php?baseClass=ilrepositorygui&ref_id={id}&cmd=edit"
+        ))?;
+        let mut form = HtmlForm::parse(
+            properties_page
+                .select(properties_form_selector)
+                .next()
+                .whatever_context(format!("Could not find properties form of {self}"))?,
+        )?;
+
+        if let Some(title) = &properties.title {
+            let title_field = form
+                .field_name_for_label(&["Titel", "Title"])
+                .unwrap_or("title")
+                .to_string();
+            form.set(&title_field, title.as_str());
+        }
+        if let Some(description) = &properties.description {
+            let description_field = form
+                .field_name_for_label(&["Beschreibung", "Description"])
+                .or(["desc", "description"]
+                    .into_iter()
+                    .find(|name| form.get(name).is_some()))
+                .unwrap_or("desc")
+                .to_string();
+            form.set(&description_field, description.as_str());
+        }
+        if let Some(online) = properties.online {
+            let online_field = form
+                .field_name_for_label(&["Online"])
+                .unwrap_or("online")
+                .to_string();
+            if online {
+                form.set(&online_field, "1");
+            } else {
+                form.remove(&online_field);
+            }
+        }
+        if let Some(availability) = &properties.availability {
+            form.set("act_type", "1");
+            form.set(
+                "access_period[start]",
+                availability
+                    .start
+                    .with_timezone(&ilias_client.timezone())
+                    .format(FORM_DATE_FORMAT)
+                    .to_string(),
+            );
+            form.set(
+                "access_period[end]",
+                availability
+                    .end
+                    .with_timezone(&ilias_client.timezone())
+                    .format(FORM_DATE_FORMAT)
+                    .to_string(),
+            );
+            if availability.visible_outside {
+                form.set("access_visiblity", "1");
+            } else {
+                form.remove("access_visiblity");
+            }
+        }
+
+        let response = form
+            .submit(ilias_client, "update")
+            .whatever_context(format!("Could not update properties of {self}"))?;
+        if ilias_client
+            .is_alert_response(response)
+            .whatever_context("Could not check error state of response")?
+        {
+            whatever!("Updating the properties of {self} failed")
+        }
+        info!("Updated properties of {self}");
+        Ok(())
+    }
+}
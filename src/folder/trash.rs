@@ -0,0 +1,271 @@
+use std::{fmt::Display, sync::OnceLock};
+
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use log::{debug, info};
+use scraper::{ElementRef, Html, Selector};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use crate::{client::IliasClient, form::HtmlForm, id::RefId, parse_date, table::TableRow};
+
+use super::{Folder, FolderElement};
+
+/// An element that was deleted from a container and can still be restored
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrashedElement {
+    pub id: RefId,
+    pub name: String,
+    pub deleted_at: Option<DateTime<Local>>,
+    pub deleted_by: Option<String>,
+}
+
+/// The outcome of [`Folder::delete_elements`]
+#[derive(Debug, Default)]
+pub struct DeletionReport {
+    /// Elements that were moved to the trash
+    pub deleted: Vec<FolderElement>,
+    /// Elements that could not be deleted, with the reason
+    pub failed: Vec<(FolderElement, String)>,
+}
+
+static DELETE_CONFIRMATION_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TRASH_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TRASH_HEADER_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TRASH_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TRASH_CHECKBOX_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static REMOVE_CONFIRMATION_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+impl Folder {
+    /// Move multiple elements of this folder to the trash with a single confirmation
+    pub fn delete_elements(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&FolderElement],
+    ) -> Result<DeletionReport, Whatever> {
+        let delete_confirmation_form_selector =
+            DELETE_CONFIRMATION_FORM_SELECTOR.get_or_init(|| {
+                Selector::parse(r#"form:has([name="cmd[confirmedDelete]"])"#)
+                    .expect("Could not parse selector")
+            });
+
+        let mut report = DeletionReport::default();
+        let mut ids = vec![];
+        for element in elements {
+            match element.id() {
+                Some(id) => ids.push(id),
+                None => report
+                    .failed
+                    .push(((*element).clone(), "Element has no id".to_string())),
+            }
+        }
+        if ids.is_empty() {
+            return Ok(report);
+        }
+
+        let confirmation_page = self.post_clipboard_command(ilias_client, "delete", &ids)?;
+        let mut confirmation_form = HtmlForm::parse(
+            confirmation_page
+                .select(delete_confirmation_form_selector)
+                .next()
+                .whatever_context("Could not find deletion confirmation")?,
+        )?;
+        if confirmation_form.get("id[]").is_none() {
+            for id in &ids {
+                confirmation_form.add("id[]", id.as_str());
+            }
+        }
+        confirmation_form
+            .submit(ilias_client, "confirmedDelete")
+            .whatever_context("Could not confirm deletion")?;
+
+        let remaining = self.reload(ilias_client)?;
+        for element in elements {
+            let Some(id) = element.id() else {
+                continue;
+            };
+            if remaining
+                .elements
+                .iter()
+                .any(|remaining| remaining.id().as_ref() == Some(&id))
+            {
+                report.failed.push((
+                    (*element).clone(),
+                    "Element is still in the folder".to_string(),
+                ));
+            } else {
+                report.deleted.push((*element).clone());
+            }
+        }
+        info!(
+            "Deleted {} of {} elements of {}",
+            report.deleted.len(),
+            elements.len(),
+            self.name
+        );
+        Ok(report)
+    }
+
+    fn trash_page(&self, ilias_client: &IliasClient) -> Result<Html, Whatever> {
+        let ref_id = self.ref_id().whatever_context("Folder has no id")?;
+        ilias_client
+            .get_querypath(&format!(
+                "ilias.php?baseClass=ilrepositorygui&ref_id={ref_id}&cmd=trash"
+            ))
+            .whatever_context(format!("Could not get trash of {}", self.name))
+    }
+
+    /// List the deleted elements of this folder
+    pub fn trash(&self, ilias_client: &IliasClient) -> Result<Vec<TrashedElement>, Whatever> {
+        let trash_header_selector = TRASH_HEADER_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"form:has(input[name="trash_id[]"]) table th"#)
+                .expect("Could not parse selector")
+        });
+        let trash_row_selector = TRASH_ROW_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"tr:has(input[name="trash_id[]"])"#)
+                .expect("Could not parse selector")
+        });
+
+        let trash_page = self.trash_page(ilias_client)?;
+        let columns = TableRow::columns(trash_page.root_element(), trash_header_selector);
+        debug!("Trash columns: {columns:?}");
+
+        let trashed = trash_page
+            .select(trash_row_selector)
+            .map(|row| TrashedElement::parse(row, &columns, ilias_client.timezone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        debug!("Trash of {}: {trashed:?}", self.name);
+        Ok(trashed)
+    }
+
+    /// Restore deleted elements to their original location
+    pub fn restore_from_trash(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&TrashedElement],
+    ) -> Result<(), Whatever> {
+        let form = self.trash_form(ilias_client, elements)?;
+        let response = form
+            .submit(ilias_client, "undelete")
+            .whatever_context("Could not restore elements")?;
+        if ilias_client
+            .is_alert_response(response)
+            .whatever_context("Could not check error state of response")?
+        {
+            whatever!("Restoring elements of {} failed", self.name)
+        }
+        info!("Restored {} elements of {}", elements.len(), self.name);
+        Ok(())
+    }
+
+    /// Permanently delete elements from the trash. This can not be undone.
+    pub fn delete_from_trash(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&TrashedElement],
+    ) -> Result<(), Whatever> {
+        let remove_confirmation_form_selector =
+            REMOVE_CONFIRMATION_FORM_SELECTOR.get_or_init(|| {
+                Selector::parse(r#"form:has([name="cmd[removeFromSystem]"])"#)
+                    .expect("Could not parse selector")
+            });
+
+        let form = self.trash_form(ilias_client, elements)?;
+        let response = form
+            .submit(ilias_client, "confirmRemoveFromSystem")
+            .whatever_context("Could not request permanent deletion")?;
+        let confirmation_page = Html::parse_document(&ilias_client.get_text(response)?);
+        let confirmation_form = HtmlForm::parse(
+            confirmation_page
+                .select(remove_confirmation_form_selector)
+                .next()
+                .whatever_context("Could not find permanent deletion confirmation")?,
+        )?;
+
+        let response = confirmation_form
+            .submit(ilias_client, "removeFromSystem")
+            .whatever_context("Could not confirm permanent deletion")?;
+        if ilias_client
+            .is_alert_response(response)
+            .whatever_context("Could not check error state of response")?
+        {
+            whatever!("Permanently deleting elements of {} failed", self.name)
+        }
+        info!(
+            "Permanently deleted {} elements of {}",
+            elements.len(),
+            self.name
+        );
+        Ok(())
+    }
+
+    /// The trash table form with exactly `elements` selected
+    fn trash_form(
+        &self,
+        ilias_client: &IliasClient,
+        elements: &[&TrashedElement],
+    ) -> Result<HtmlForm, Whatever> {
+        let trash_form_selector = TRASH_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"form:has(input[name="trash_id[]"])"#)
+                .expect("Could not parse selector")
+        });
+
+        let trash_page = self.trash_page(ilias_client)?;
+        let mut form = HtmlForm::parse(
+            trash_page
+                .select(trash_form_selector)
+                .next()
+                .whatever_context(format!("The trash of {} is empty", self.name))?,
+        )?;
+        form.remove("trash_id[]");
+        for element in elements {
+            form.add("trash_id[]", element.id.as_str());
+        }
+        Ok(form)
+    }
+}
+
+impl TrashedElement {
+    fn parse(
+        row: ElementRef,
+        columns: &[String],
+        timezone: Tz,
+    ) -> Result<TrashedElement, Whatever> {
+        let trash_checkbox_selector = TRASH_CHECKBOX_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"input[name="trash_id[]"]"#).expect("Could not parse selector")
+        });
+
+        let id = RefId::new(
+            row.select(trash_checkbox_selector)
+                .next()
+                .and_then(|checkbox| checkbox.attr("value"))
+                .whatever_context("Trash row has no id")?,
+        );
+
+        let table_row = TableRow::parse(row, columns);
+
+        let name = table_row
+            .value(&["Titel", "Title"])
+            .whatever_context("Could not find name of trashed element")?
+            .to_string();
+        let deleted_at = table_row
+            .value(&["Gelöscht am", "Deleted On", "Deleted on"])
+            .and_then(|date| parse_date(date, timezone).ok());
+        let deleted_by = table_row
+            .value(&["Gelöscht von", "Deleted By", "Deleted by"])
+            .cloned();
+
+        Ok(TrashedElement {
+            id,
+            name,
+            deleted_at,
+            deleted_by,
+        })
+    }
+}
+
+impl Display for TrashedElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
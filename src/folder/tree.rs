@@ -0,0 +1,170 @@
+use std::{collections::HashSet, fmt::Display};
+
+use log::debug;
+use snafu::{ResultExt, Whatever};
+
+use crate::{IliasElement, client::IliasClient, id::RefId};
+
+use super::{ElementKind, Folder, FolderElement};
+
+/// Options for [`Folder::walk`]
+#[derive(Clone, Debug, Default)]
+pub struct WalkOptions {
+    /// How many levels of sub-containers to resolve. `None` resolves the whole tree, `Some(0)`
+    /// only lists the elements of the folder itself.
+    pub max_depth: Option<usize>,
+    /// Only keep elements of these kinds. Sub-containers are still resolved if they are filtered
+    /// out, but they only show up in the tree if they contain matching elements.
+    pub kinds: Option<Vec<ElementKind>>,
+}
+
+/// A folder together with all of its (recursively resolved) sub-containers
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FolderTree {
+    pub name: String,
+    pub nodes: Vec<FolderTreeNode>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FolderTreeNode {
+    Element(FolderElement),
+    Container {
+        element: FolderElement,
+        tree: FolderTree,
+    },
+}
+
+/// An element of a [`FolderTree`] with the names of all containers leading to it
+#[derive(Clone, Debug)]
+pub struct TreeEntry<'a> {
+    pub path: Vec<String>,
+    pub element: &'a FolderElement,
+}
+
+impl Folder {
+    /// Recursively resolve all sub-containers (folders, courses, groups) of this folder.
+    /// Containers that were already visited are not resolved again, so links between containers
+    /// can not lead to endless recursion.
+    pub fn walk(
+        &self,
+        ilias_client: &IliasClient,
+        options: &WalkOptions,
+    ) -> Result<FolderTree, Whatever> {
+        let mut visited = HashSet::new();
+        if let Some(ref_id) = self.ref_id() {
+            visited.insert(ref_id);
+        }
+
+        FolderTree::build(self, ilias_client, options, 0, &mut visited)
+    }
+}
+
+impl FolderTree {
+    fn build(
+        folder: &Folder,
+        ilias_client: &IliasClient,
+        options: &WalkOptions,
+        depth: usize,
+        visited: &mut HashSet<RefId>,
+    ) -> Result<FolderTree, Whatever> {
+        let mut nodes = vec![];
+        for element in &folder.elements {
+            let is_included = options
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&element.kind()));
+
+            let FolderElement::Viewable { id, querypath, .. } = element else {
+                if is_included {
+                    nodes.push(FolderTreeNode::Element(element.clone()));
+                }
+                continue;
+            };
+
+            let may_descend = options.max_depth.is_none_or(|max_depth| depth < max_depth);
+            if !may_descend || !visited.insert(id.clone()) {
+                debug!("Not descending into {element}");
+                if is_included {
+                    nodes.push(FolderTreeNode::Element(element.clone()));
+                }
+                continue;
+            }
+
+            let sub_folder = ilias_client
+                .get_querypath(querypath)
+                .and_then(|page| Folder::parse(page.root_element(), ilias_client))
+                .whatever_context(format!("Could not parse sub-container {element}"))?;
+            let tree = FolderTree::build(&sub_folder, ilias_client, options, depth + 1, visited)?;
+
+            if is_included || !tree.nodes.is_empty() {
+                nodes.push(FolderTreeNode::Container {
+                    element: element.clone(),
+                    tree,
+                });
+            }
+        }
+
+        Ok(FolderTree {
+            name: folder.name.clone(),
+            nodes,
+        })
+    }
+
+    /// All elements of the tree in depth-first order. The path of each entry starts with the name
+    /// of the root folder and ends with the name of the element.
+    pub fn entries(&self) -> Vec<TreeEntry<'_>> {
+        let mut entries = vec![];
+        self.collect_entries(&[sanitize_name(&self.name)], &mut entries);
+        entries
+    }
+
+    fn collect_entries<'a>(&'a self, parent_path: &[String], entries: &mut Vec<TreeEntry<'a>>) {
+        for node in &self.nodes {
+            let element = node.element();
+            let mut path = parent_path.to_vec();
+            path.push(sanitize_name(element.name()));
+
+            entries.push(TreeEntry {
+                path: path.clone(),
+                element,
+            });
+            if let FolderTreeNode::Container { tree, .. } = node {
+                tree.collect_entries(&path, entries);
+            }
+        }
+    }
+}
+
+impl FolderTreeNode {
+    pub fn element(&self) -> &FolderElement {
+        match self {
+            Self::Element(element) | Self::Container { element, .. } => element,
+        }
+    }
+}
+
+impl TreeEntry<'_> {
+    /// The path joined with `/`, e.g. `Course/Übungen/Blatt 3/aufgabe.pdf`
+    pub fn path_string(&self) -> String {
+        self.path.join("/")
+    }
+}
+
+impl Display for TreeEntry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path_string())
+    }
+}
+
+/// Element names may contain path separators or be `.` or `..`, which must not end up in local
+/// paths
+pub(crate) fn sanitize_name(name: &str) -> String {
+    let name = name.trim().replace(std::path::is_separator, "-");
+    match name.as_str() {
+        "" => "_".to_string(),
+        "." | ".." => name.replace('.', "_"),
+        _ => name,
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono_tz::Tz;
+use log::{debug, info};
+use snafu::{OptionExt, ResultExt, Whatever};
+
+use crate::{
+    client::IliasClient, file::VersionMode, form::HtmlForm, id::RefId, local_file::NamedLocalFile,
+};
+
+use super::{
+    Folder, FolderElement,
+    properties::{AvailabilityPeriod, FORM_DATE_FORMAT},
+};
+
+/// Settings applied to every file uploaded with [`Folder::upload_files_with_options`]
+#[derive(Clone, Debug, Default)]
+pub struct UploadOptions {
+    pub description: String,
+    /// Value or label of one of the [`Folder::upload_license_options`]. `None` uploads the files
+    /// as "All rights reserved".
+    pub license: Option<String>,
+    /// Upload the files offline
+    pub offline: bool,
+    /// Only make the files available to members during this period
+    pub availability: Option<AvailabilityPeriod>,
+}
+
+/// How ILIAS unpacks a zip archive uploaded with [`Folder::upload_zip`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnzipMode {
+    /// Put all files of the archive directly into the folder
+    Flatten,
+    /// Create folders for the directories in the archive
+    KeepStructure,
+}
+
+/// Options for [`Folder::upload_directory`]
+#[derive(Clone, Debug, Default)]
+pub struct DirectoryUploadOptions {
+    /// Applied to every uploaded file
+    pub upload: UploadOptions,
+    /// Upload a new version of remote files that have the same name as a local file instead of
+    /// skipping them
+    pub replace_existing: bool,
+}
+
+/// What [`Folder::upload_directory`] did, as paths relative to the uploaded directory
+#[derive(Clone, Debug, Default)]
+pub struct DirectoryUploadReport {
+    pub uploaded: Vec<PathBuf>,
+    pub replaced: Vec<PathBuf>,
+    /// Files that exist remotely already and symlinks, which are never followed
+    pub skipped: Vec<PathBuf>,
+    pub created_folders: Vec<PathBuf>,
+}
+
+impl Folder {
+    /// Upload a zip archive and let ILIAS extract it into this folder, which is a lot faster than
+    /// uploading many files one by one. Returns the new elements of this folder, with
+    /// [`UnzipMode::KeepStructure`] these are the top level folders of the archive.
+    pub fn upload_zip(
+        &self,
+        ilias_client: &IliasClient,
+        archive: &NamedLocalFile,
+        mode: UnzipMode,
+        options: &UploadOptions,
+    ) -> Result<Vec<FolderElement>, Whatever> {
+        let known_ids = self.current_ids(ilias_client)?;
+        self.submit_uploads(
+            ilias_client,
+            std::slice::from_ref(archive),
+            options,
+            Some(mode),
+        )?;
+        info!("Uploaded and extracted {} into {}", archive.name, self.name);
+
+        self.new_elements(ilias_client, &known_ids)
+    }
+
+    /// Upload the contents of a local directory into this folder, creating remote sub-folders
+    /// for local sub-directories as needed. Remote files with the same name as a local file are
+    /// skipped unless [`DirectoryUploadOptions::replace_existing`] is set, in which case the local
+    /// file is uploaded as a new version of the remote one.
+    pub fn upload_directory(
+        &self,
+        ilias_client: &IliasClient,
+        directory: &Path,
+        options: &DirectoryUploadOptions,
+    ) -> Result<DirectoryUploadReport, Whatever> {
+        let mut report = DirectoryUploadReport::default();
+        self.upload_directory_into(ilias_client, directory, Path::new(""), options, &mut report)?;
+        Ok(report)
+    }
+
+    fn upload_directory_into(
+        &self,
+        ilias_client: &IliasClient,
+        directory: &Path,
+        relative_path: &Path,
+        options: &DirectoryUploadOptions,
+        report: &mut DirectoryUploadReport,
+    ) -> Result<(), Whatever> {
+        let mut entries = fs::read_dir(directory)
+            .whatever_context(format!("Could not read directory {directory:?}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .whatever_context(format!("Could not read entry of {directory:?}"))?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut files = vec![];
+        let mut file_relative_paths = vec![];
+        let mut sub_directories = vec![];
+        for entry in entries {
+            let path = entry.path();
+            let name = entry
+                .file_name()
+                .to_str()
+                .whatever_context(format!("File name of {path:?} is not valid UTF-8"))?
+                .to_string();
+            let entry_relative_path = relative_path.join(&name);
+            let file_type = entry
+                .file_type()
+                .whatever_context(format!("Could not get file type of {path:?}"))?;
+            if file_type.is_symlink() {
+                debug!("Skipping symlink {entry_relative_path:?}");
+                report.skipped.push(entry_relative_path);
+                continue;
+            }
+            if file_type.is_dir() {
+                sub_directories.push((name, path));
+                continue;
+            }
+
+            let existing = self.elements.iter().find(
+                |element| matches!(element, FolderElement::File { file, .. } if file.name == name),
+            );
+            match existing {
+                Some(existing) if options.replace_existing => {
+                    debug!("Replacing {entry_relative_path:?}");
+                    existing
+                        .upload_new_version(
+                            ilias_client,
+                            &NamedLocalFile { name, path },
+                            VersionMode::Add,
+                        )
+                        .whatever_context(format!("Could not replace {entry_relative_path:?}"))?;
+                    report.replaced.push(entry_relative_path);
+                    continue;
+                }
+                Some(_) => {
+                    debug!("Skipping existing {entry_relative_path:?}");
+                    report.skipped.push(entry_relative_path);
+                    continue;
+                }
+                None => {}
+            }
+            files.push(NamedLocalFile { name, path });
+            file_relative_paths.push(entry_relative_path);
+        }
+
+        if !files.is_empty() {
+            // Existing files are looked up in `self.elements` as well, so it serves as the snapshot
+            let known_ids: Vec<RefId> =
+                self.elements.iter().filter_map(FolderElement::id).collect();
+            self.upload_new_files(ilias_client, &files, &options.upload, &known_ids)
+                .whatever_context(format!("Could not upload files of {directory:?}"))?;
+            info!("Uploaded {} files to {}", files.len(), self.name);
+            report.uploaded.extend(file_relative_paths);
+        }
+
+        for (name, path) in sub_directories {
+            let sub_relative_path = relative_path.join(&name);
+            let sub_folder = match self.sub_folder(ilias_client, &name)? {
+                Some(sub_folder) => sub_folder,
+                None => {
+                    let sub_folder = self
+                        .create_folder(ilias_client, &name, "")
+                        .whatever_context(format!("Could not create folder {name}"))?;
+                    report.created_folders.push(sub_relative_path.clone());
+                    sub_folder
+                }
+            };
+            sub_folder.upload_directory_into(
+                ilias_client,
+                &path,
+                &sub_relative_path,
+                options,
+                report,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The fields of the upload form that set the online status and availability of `options`. They
+/// are sent with the upload, so the files are never online without the restrictions.
+pub(super) fn upload_property_fields(
+    upload_form: &HtmlForm,
+    options: &UploadOptions,
+    timezone: Tz,
+) -> Result<Vec<(String, String)>, Whatever> {
+    let mut fields = vec![];
+    if !options.offline && options.availability.is_none() {
+        return Ok(fields);
+    }
+
+    let online_field = upload_form
+        .field_name_for_label(&["Online"])
+        .whatever_context("The upload form does not allow setting the online status")?;
+    if !options.offline {
+        fields.push((online_field.to_string(), "checked".to_string()));
+    }
+
+    if let Some(availability) = &options.availability {
+        let field = |labels: &[&str], description: &str| {
+            upload_form
+                .field_name_for_label(labels)
+                .map(str::to_string)
+                .whatever_context(format!(
+                    "The upload form has no field for the {description}"
+                ))
+        };
+        fields.push((
+            field(&["Verfügbarkeit", "Availability"], "availability")?,
+            "checked".to_string(),
+        ));
+        fields.push((
+            field(&["Start", "Beginn"], "availability start")?,
+            availability
+                .start
+                .with_timezone(&timezone)
+                .format(FORM_DATE_FORMAT)
+                .to_string(),
+        ));
+        fields.push((
+            field(&["Ende", "End"], "availability end")?,
+            availability
+                .end
+                .with_timezone(&timezone)
+                .format(FORM_DATE_FORMAT)
+                .to_string(),
+        ));
+        if availability.visible_outside {
+            fields.push((
+                field(
+                    &["Sichtbarkeit", "Visibility", "Sichtbar", "Visible"],
+                    "visibility outside of the availability period",
+                )?,
+                "checked".to_string(),
+            ));
+        }
+    }
+    Ok(fields)
+}
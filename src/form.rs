@@ -0,0 +1,213 @@
+use std::sync::OnceLock;
+
+use reqwest::Response;
+use scraper::{ElementRef, Selector, selectable::Selectable};
+use snafu::{OptionExt, Whatever};
+
+use super::client::IliasClient;
+
+/// A HTML form with the values it was rendered with. Submitting it sends all fields ILIAS
+/// pre-filled (including hidden ones), so only the fields of interest need to be changed.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlForm {
+    pub action: String,
+    fields: Vec<(String, String)>,
+    buttons: Vec<(String, String)>,
+    labels: Vec<(String, String)>,
+    options: Vec<(String, Vec<SelectOption>)>,
+}
+
+/// An option of a `<select>` field
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectOption {
+    pub value: String,
+    pub label: String,
+}
+
+static INPUT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static BUTTON_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TEXTAREA_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static SELECT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static OPTION_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static LABEL_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static NAMED_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+impl HtmlForm {
+    pub fn parse(form: ElementRef) -> Result<HtmlForm, Whatever> {
+        let input_selector = INPUT_SELECTOR
+            .get_or_init(|| Selector::parse("input[name]").expect("Could not parse selector"));
+        let button_selector = BUTTON_SELECTOR
+            .get_or_init(|| Selector::parse("button[name]").expect("Could not parse selector"));
+        let textarea_selector = TEXTAREA_SELECTOR
+            .get_or_init(|| Selector::parse("textarea[name]").expect("Could not parse selector"));
+        let select_selector = SELECT_SELECTOR
+            .get_or_init(|| Selector::parse("select[name]").expect("Could not parse selector"));
+        let option_selector = OPTION_SELECTOR
+            .get_or_init(|| Selector::parse("option").expect("Could not parse selector"));
+        let label_selector = LABEL_SELECTOR
+            .get_or_init(|| Selector::parse("label[for]").expect("Could not parse selector"));
+        let named_selector = NAMED_SELECTOR
+            .get_or_init(|| Selector::parse("[id][name]").expect("Could not parse selector"));
+
+        let action = form
+            .attr("action")
+            .whatever_context("Form has no action")?
+            .to_string();
+
+        let mut fields = vec![];
+        let mut buttons = vec![];
+        for input in form.select(input_selector) {
+            let name = input.attr("name").unwrap().to_string();
+            let value = input.attr("value").unwrap_or_default().to_string();
+            match input.attr("type").unwrap_or("text") {
+                "submit" => buttons.push((name, value)),
+                "button" | "file" | "image" | "reset" => {}
+                "checkbox" | "radio" => {
+                    if input.attr("checked").is_some() {
+                        fields.push((name, value));
+                    }
+                }
+                _ => fields.push((name, value)),
+            }
+        }
+        for button in form.select(button_selector) {
+            if button.attr("type").unwrap_or("submit") == "submit" {
+                let name = button.attr("name").unwrap().to_string();
+                let value = button
+                    .attr("value")
+                    .map(str::to_string)
+                    .unwrap_or_else(|| button.text().collect::<String>().trim().to_string());
+                buttons.push((name, value));
+            }
+        }
+        for textarea in form.select(textarea_selector) {
+            let name = textarea.attr("name").unwrap().to_string();
+            fields.push((name, textarea.text().collect()));
+        }
+
+        let mut options = vec![];
+        for select in form.select(select_selector) {
+            let name = select.attr("name").unwrap().to_string();
+            let select_options: Vec<SelectOption> = select
+                .select(option_selector)
+                .map(|option| SelectOption {
+                    value: option
+                        .attr("value")
+                        .map(str::to_string)
+                        .unwrap_or_else(|| option.text().collect()),
+                    label: option.text().collect::<String>().trim().to_string(),
+                })
+                .collect();
+            let selected = select
+                .select(option_selector)
+                .position(|option| option.attr("selected").is_some())
+                .unwrap_or(0);
+            if let Some(option) = select_options.get(selected) {
+                fields.push((name.clone(), option.value.clone()));
+            }
+            options.push((name, select_options));
+        }
+
+        let labels = form
+            .select(label_selector)
+            .filter_map(|label| {
+                let target_id = label.attr("for")?;
+                let name = form
+                    .select(named_selector)
+                    .find(|field| field.attr("id") == Some(target_id))?
+                    .attr("name")?;
+                Some((
+                    label.text().collect::<String>().trim().to_string(),
+                    name.to_string(),
+                ))
+            })
+            .collect();
+
+        Ok(HtmlForm {
+            action,
+            fields,
+            buttons,
+            labels,
+            options,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replace all values of the field `name` with `value`
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        self.remove(name);
+        self.fields.push((name.to_string(), value.into()));
+    }
+
+    /// Add another value for `name`, e.g. for array fields like `id[]`
+    pub fn add(&mut self, name: &str, value: impl Into<String>) {
+        self.fields.push((name.to_string(), value.into()));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.fields.retain(|(field_name, _)| field_name != name);
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    /// The name of the field labeled with one of `labels`, useful for forms with generated field
+    /// names
+    pub fn field_name_for_label(&self, labels: &[&str]) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(label, _)| {
+                labels
+                    .iter()
+                    .any(|wanted| label.trim_end_matches(['*', ' ']) == *wanted)
+            })
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// The available options of the select field `name`
+    pub fn select_options(&self, name: &str) -> &[SelectOption] {
+        self.options
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, options)| options.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The value of the submit button `name`
+    pub fn button(&self, name: &str) -> Option<&str> {
+        self.buttons
+            .iter()
+            .find(|(button_name, _)| button_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Submit the form by pressing the submit button `name`
+    pub fn submit_with_button(
+        &self,
+        ilias_client: &IliasClient,
+        name: &str,
+    ) -> Result<Response, Whatever> {
+        let value = self
+            .button(name)
+            .whatever_context(format!("Form has no button {name}"))?;
+        let mut fields = self.fields.clone();
+        fields.push((name.to_string(), value.to_string()));
+        ilias_client.post_querypath_form(&self.action, &fields)
+    }
+
+    /// Submit the form, pressing the button for `command` (i.e. sending `cmd[command]`)
+    pub fn submit(&self, ilias_client: &IliasClient, command: &str) -> Result<Response, Whatever> {
+        let mut fields = self.fields.clone();
+        fields.push((format!("cmd[{command}]"), command.to_string()));
+        ilias_client.post_querypath_form(&self.action, &fields)
+    }
+}
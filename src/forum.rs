@@ -0,0 +1,510 @@
+use std::{fmt::Display, sync::OnceLock};
+
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use log::{debug, info};
+use regex::Regex;
+use reqwest::multipart::Form;
+use scraper::{ElementRef, Html, Selector, selectable::Selectable};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use super::{
+    IliasElement,
+    client::{AddFileWithFilename, IliasClient},
+    file::File,
+    id::RefId,
+    local_file::NamedLocalFile,
+    parse_date,
+    querypath::Querypath,
+    reference::Reference,
+};
+
+/// An ILIAS forum with the threads listed on its overview page
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Forum {
+    pub name: String,
+    pub description: String,
+    pub threads: Vec<ForumThread>,
+    new_thread_querypath: Option<Querypath>,
+}
+
+/// A single thread as listed on the forum overview page. The posts of the thread are only fetched
+/// on demand via [`ForumThread::get_posts`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForumThread {
+    pub title: String,
+    pub id: String,
+    pub author: Option<String>,
+    pub date: Option<DateTime<Local>>,
+    pub unread: bool,
+    pub querypath: Querypath,
+    posts: Reference<Vec<ForumPost>>,
+}
+
+/// A post inside of a forum thread
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForumPost {
+    pub id: Option<String>,
+    pub subject: String,
+    pub author: Option<String>,
+    pub date: Option<DateTime<Local>>,
+    pub body_html: String,
+    pub attachments: Vec<File>,
+    reply_querypath: Option<Querypath>,
+}
+
+/// Content of a new thread or reply that should be posted to a forum
+#[derive(Debug, Clone)]
+pub struct ForumPostDraft {
+    pub subject: String,
+    /// The message of the post, ILIAS interprets it as HTML
+    pub body: String,
+    pub attachments: Vec<NamedLocalFile>,
+    /// Whether to get notified by ILIAS about replies
+    pub notify: bool,
+}
+
+static NAME_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static DESCRIPTION_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static THREAD_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static NEW_THREAD_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+impl IliasElement for Forum {
+    fn type_identifier() -> Option<&'static str> {
+        Some("frm")
+    }
+
+    fn querypath_from_id(id: &RefId) -> Option<String> {
+        Some(format!(
+            "goto.php/{}/{}",
+            Self::type_identifier().unwrap(),
+            id
+        ))
+    }
+
+    fn parse(element: ElementRef, ilias_client: &IliasClient) -> Result<Self, Whatever> {
+        let name_selector = NAME_SELECTOR.get_or_init(|| {
+            Selector::parse(".il-page-content-header").expect("Could not parse selector")
+        });
+        let description_selector = DESCRIPTION_SELECTOR
+            .get_or_init(|| Selector::parse(".ilHeaderDesc").expect("Could not parse selector"));
+        let thread_row_selector = THREAD_ROW_SELECTOR.get_or_init(|| {
+            Selector::parse("#ilContentContainer table tbody tr").expect("Could not parse selector")
+        });
+        let new_thread_selector = NEW_THREAD_SELECTOR.get_or_init(|| {
+            Selector::parse(
+                r#"a[href*="cmd=createThread"], button[data-action*="cmd=createThread"]"#,
+            )
+            .expect("Could not parse selector")
+        });
+
+        let name = element
+            .select(name_selector)
+            .next()
+            .whatever_context("Could not find name")?
+            .text()
+            .collect::<String>()
+            .trim()
+            .to_string();
+        let description = element
+            .select(description_selector)
+            .next()
+            .map(|description| description.text().collect())
+            .unwrap_or_default();
+
+        let mut threads = vec![];
+        for row in element.select(thread_row_selector) {
+            if let Some(thread) = ForumThread::parse(row, ilias_client.timezone())
+                .whatever_context("Could not parse forum thread")?
+            {
+                threads.push(thread);
+            }
+        }
+        debug!("Threads: {threads:?}");
+
+        let new_thread_querypath = element
+            .select(new_thread_selector)
+            .next()
+            .and_then(|button| button.attr("href").or(button.attr("data-action")))
+            .map(Querypath::parse);
+
+        Ok(Forum {
+            name,
+            description,
+            threads,
+            new_thread_querypath,
+        })
+    }
+}
+
+impl Forum {
+    pub fn new_thread_querypath(&self) -> Option<&Querypath> {
+        self.new_thread_querypath.as_ref()
+    }
+
+    /// Open a new thread in this forum and return the permalink of its first post
+    pub fn create_thread(
+        &self,
+        ilias_client: &IliasClient,
+        draft: &ForumPostDraft,
+    ) -> Result<String, Whatever> {
+        let new_thread_querypath = self
+            .new_thread_querypath
+            .as_ref()
+            .whatever_context(format!("You can not create threads in forum {}", self.name))?;
+        let permalink = draft
+            .submit(ilias_client, new_thread_querypath, "addThread")
+            .whatever_context(format!("Could not create thread in forum {}", self.name))?;
+        info!("Created thread {} at {permalink}", draft.subject);
+        Ok(permalink)
+    }
+}
+
+static THREAD_LINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static USER_LINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static UNREAD_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+static THREAD_ID_REGEX: OnceLock<Regex> = OnceLock::new();
+
+impl ForumThread {
+    /// The posts as far as they were fetched, see [`ForumThread::get_posts`]
+    pub fn posts(&self) -> &Reference<Vec<ForumPost>> {
+        &self.posts
+    }
+
+    /// Parse a row of the thread table. Returns `None` for rows that do not link to a thread (e.g.
+    /// the "no entries" row of an empty forum).
+    fn parse(row: ElementRef, timezone: Tz) -> Result<Option<ForumThread>, Whatever> {
+        let thread_link_selector = THREAD_LINK_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"a[href*="thr_pk="]"#).expect("Could not parse selector")
+        });
+        let user_link_selector = USER_LINK_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"a[href*="ilpublicuserprofilegui"], a[href*="cmd=showUserProfile"]"#)
+                .expect("Could not parse selector")
+        });
+        let unread_selector = UNREAD_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"[class*="unread"], [class*="Unread"]"#)
+                .expect("Could not parse selector")
+        });
+        let thread_id_regex = THREAD_ID_REGEX
+            .get_or_init(|| Regex::new(r"thr_pk=(?<id>\d+)").expect("Could not parse regex"));
+
+        let Some(thread_link) = row.select(thread_link_selector).next() else {
+            debug!("Skipping row without thread link");
+            return Ok(None);
+        };
+
+        let title = thread_link.text().collect::<String>().trim().to_string();
+        let querypath = Querypath::parse(
+            thread_link
+                .attr("href")
+                .whatever_context("Thread link had no href")?,
+        );
+        let id = thread_id_regex
+            .captures(&querypath)
+            .whatever_context(format!("Could not find thread id in {querypath}"))?["id"]
+            .to_string();
+
+        let author = row
+            .select(user_link_selector)
+            .next()
+            .map(|author| author.text().collect::<String>().trim().to_string());
+        let date = row
+            .text()
+            .map(str::trim)
+            .find_map(|text| parse_date(text, timezone).ok());
+        let unread = row.select(unread_selector).next().is_some();
+
+        Ok(Some(ForumThread {
+            title,
+            id,
+            author,
+            date,
+            unread,
+            posts: Reference::Unresolved(querypath.clone()),
+            querypath,
+        }))
+    }
+
+    pub fn get_posts(&mut self, ilias_client: &IliasClient) -> Result<&[ForumPost], Whatever> {
+        let posts = &mut self.posts;
+        if let Reference::Unresolved(querypath) = posts {
+            let thread_page = ilias_client
+                .get_querypath(querypath.as_str())
+                .whatever_context("Could not get thread page")?;
+            let parsed_posts =
+                ForumPost::parse_thread_page(thread_page.root_element(), ilias_client.timezone())
+                    .whatever_context("Could not parse thread page")?;
+            *posts = Reference::Resolved(parsed_posts);
+        }
+
+        Ok(posts
+            .try_get_resolved()
+            .whatever_context("Posts of thread are unavailable")?)
+    }
+}
+
+impl Display for ForumThread {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.title)
+    }
+}
+
+static POST_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static POST_SUBJECT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static POST_HEADER_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static POST_CONTENT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static POST_ANCHOR_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static POST_LINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static POST_REPLY_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static ATTACHMENT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+static POST_ID_REGEX: OnceLock<Regex> = OnceLock::new();
+
+impl ForumPost {
+    pub fn reply_querypath(&self) -> Option<&Querypath> {
+        self.reply_querypath.as_ref()
+    }
+
+    fn parse_thread_page(
+        thread_page: ElementRef,
+        timezone: Tz,
+    ) -> Result<Vec<ForumPost>, Whatever> {
+        let post_selector = POST_SELECTOR
+            .get_or_init(|| Selector::parse(".ilFrmPostRow").expect("Could not parse selector"));
+
+        let mut posts = vec![];
+        for post in thread_page.select(post_selector) {
+            posts.push(ForumPost::parse(post, timezone).whatever_context("Could not parse post")?);
+        }
+        debug!("Posts: {posts:?}");
+
+        Ok(posts)
+    }
+
+    fn parse(element: ElementRef, timezone: Tz) -> Result<ForumPost, Whatever> {
+        let post_subject_selector = POST_SUBJECT_SELECTOR
+            .get_or_init(|| Selector::parse(".ilFrmPostTitle").expect("Could not parse selector"));
+        let post_header_selector = POST_HEADER_SELECTOR
+            .get_or_init(|| Selector::parse(".ilFrmPostHeader").expect("Could not parse selector"));
+        let post_content_selector = POST_CONTENT_SELECTOR.get_or_init(|| {
+            Selector::parse(".ilFrmPostContent").expect("Could not parse selector")
+        });
+        let post_anchor_selector = POST_ANCHOR_SELECTOR
+            .get_or_init(|| Selector::parse("a[id]").expect("Could not parse selector"));
+        let post_link_selector = POST_LINK_SELECTOR
+            .get_or_init(|| Selector::parse("a[href]").expect("Could not parse selector"));
+        let post_reply_selector = POST_REPLY_SELECTOR.get_or_init(|| {
+            Selector::parse(
+                r#"a[href*="action=showreply"], button[data-action*="action=showreply"]"#,
+            )
+            .expect("Could not parse selector")
+        });
+        let attachment_selector = ATTACHMENT_SELECTOR.get_or_init(|| {
+            Selector::parse(".ilFrmPostAttachmentsContainer a[href]")
+                .expect("Could not parse selector")
+        });
+        let user_link_selector = USER_LINK_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"a[href*="ilpublicuserprofilegui"], a[href*="cmd=showUserProfile"]"#)
+                .expect("Could not parse selector")
+        });
+        let post_id_regex = POST_ID_REGEX
+            .get_or_init(|| Regex::new(r"pos_pk=(?<id>\d+)").expect("Could not parse regex"));
+
+        let subject = element
+            .select(post_subject_selector)
+            .next()
+            .whatever_context("Could not find post subject")?
+            .text()
+            .collect::<String>()
+            .trim()
+            .to_string();
+        let header = element
+            .select(post_header_selector)
+            .next()
+            .whatever_context("Could not find post header")?;
+        let author = header
+            .select(user_link_selector)
+            .next()
+            .map(|author| author.text().collect::<String>().trim().to_string());
+        let date = header
+            .text()
+            .map(str::trim)
+            .find_map(|text| parse_date(text, timezone).ok());
+        let body_html = element
+            .select(post_content_selector)
+            .next()
+            .whatever_context(format!("Could not find content of post {subject}"))?
+            .inner_html()
+            .trim()
+            .to_string();
+
+        let id = element
+            .select(post_anchor_selector)
+            .filter_map(|anchor| anchor.attr("id"))
+            .find(|id| id.chars().all(|c| c.is_ascii_digit()))
+            .map(str::to_string)
+            .or_else(|| {
+                element
+                    .select(post_link_selector)
+                    .filter_map(|link| link.attr("href"))
+                    .find_map(|href| Some(post_id_regex.captures(href)?["id"].to_string()))
+            });
+
+        let attachments = element
+            .select(attachment_selector)
+            .map(|attachment| File {
+                name: attachment.text().collect::<String>().trim().to_string(),
+                description: String::new(),
+                date: None,
+                download_querypath: attachment.attr("href").map(Querypath::parse),
+                id: None,
+                size: None,
+            })
+            .collect();
+
+        let reply_querypath = element
+            .select(post_reply_selector)
+            .next()
+            .and_then(|button| button.attr("href").or(button.attr("data-action")))
+            .map(Querypath::parse);
+
+        Ok(ForumPost {
+            id,
+            subject,
+            author,
+            date,
+            body_html,
+            attachments,
+            reply_querypath,
+        })
+    }
+
+    /// Reply to this post and return the permalink of the reply
+    pub fn reply(
+        &self,
+        ilias_client: &IliasClient,
+        draft: &ForumPostDraft,
+    ) -> Result<String, Whatever> {
+        let reply_querypath = self
+            .reply_querypath
+            .as_ref()
+            .whatever_context(format!("You can not reply to post {}", self.subject))?;
+        let permalink = draft
+            .submit(ilias_client, reply_querypath, "savePost")
+            .whatever_context(format!("Could not reply to post {}", self.subject))?;
+        info!("Replied to {} at {permalink}", self.subject);
+        Ok(permalink)
+    }
+}
+
+impl Display for ForumPost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.subject)
+    }
+}
+
+static POST_FORM_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static HIDDEN_INPUT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static PERMALINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static ALERT_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+static PERMALINK_ID_REGEX: OnceLock<Regex> = OnceLock::new();
+
+impl ForumPostDraft {
+    /// Fill in the post form found on the page at `form_page_querypath`, submit it with `command`
+    /// and return the permalink querypath of the created post.
+    fn submit(
+        &self,
+        ilias_client: &IliasClient,
+        form_page_querypath: &str,
+        command: &str,
+    ) -> Result<String, Whatever> {
+        let post_form_selector = POST_FORM_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"form:has(textarea[name="message"])"#)
+                .expect("Could not parse selector")
+        });
+        let hidden_input_selector = HIDDEN_INPUT_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"input[type="hidden"]"#).expect("Could not parse selector")
+        });
+        let permalink_selector = PERMALINK_SELECTOR.get_or_init(|| {
+            Selector::parse("input#current_perma_link").expect("Could not parse selector")
+        });
+        let alert_selector = ALERT_SELECTOR
+            .get_or_init(|| Selector::parse(".alert-danger").expect("Could not parse selector"));
+        let permalink_id_regex = PERMALINK_ID_REGEX.get_or_init(|| {
+            Regex::new(r"(?<key>ref_id|thr_pk|pos_pk)=(?<id>\d+)").expect("Could not parse regex")
+        });
+
+        let form_page = ilias_client
+            .get_querypath(form_page_querypath)
+            .whatever_context("Could not get post form page")?;
+        let post_form = form_page
+            .select(post_form_selector)
+            .next()
+            .whatever_context("Did not find post form")?;
+        let post_querypath = post_form
+            .attr("action")
+            .whatever_context("Post form had no action")?;
+        debug!("Post querypath: {post_querypath}");
+
+        let mut form = Form::new();
+        for hidden_input in post_form.select(hidden_input_selector) {
+            if let (Some(name), Some(value)) =
+                (hidden_input.attr("name"), hidden_input.attr("value"))
+            {
+                form = form.text(name.to_string(), value.to_string());
+            }
+        }
+        form = form
+            .text("subject", self.subject.clone())
+            .text("message", self.body.clone())
+            .text(format!("cmd[{command}]"), "Speichern");
+        if self.notify {
+            form = form.text("notify", "1");
+        }
+        for attachment in &self.attachments {
+            form = form.file_with_name(
+                "userfile[]",
+                ilias_client.construct_file_part(&attachment.path),
+                attachment.name.clone(),
+            )?;
+        }
+
+        let response = ilias_client
+            .post_querypath_multipart(post_querypath, form)
+            .whatever_context("Could not post forum form")?;
+        let response_url = response.url().to_string();
+        debug!("Post response url: {response_url}");
+        let response_page = Html::parse_document(&ilias_client.get_text(response)?);
+        if response_page.select(alert_selector).next().is_some() {
+            whatever!("ILIAS rejected the post {}", self.subject);
+        }
+
+        let find_id = |key: &str| {
+            permalink_id_regex
+                .captures_iter(&response_url)
+                .find(|captures| &captures["key"] == key)
+                .map(|captures| captures["id"].to_string())
+        };
+        if let (Some(ref_id), Some(thread_id), Some(post_id)) =
+            (find_id("ref_id"), find_id("thr_pk"), find_id("pos_pk"))
+        {
+            return Ok(format!(
+                "goto.php?target=frm_{ref_id}_{thread_id}_{post_id}&client_id={}",
+                ilias_client.client_id()?
+            ));
+        }
+        let permalink = response_page
+            .select(permalink_selector)
+            .next()
+            .and_then(|permalink| permalink.attr("value"))
+            .whatever_context("Could not find permalink of created post")?;
+        ilias_client
+            .querypath_of(permalink)
+            .whatever_context(format!("Permalink {permalink} does not point to ILIAS"))
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt::Display;
+
+/// Defines a newtype for one kind of ILIAS id
+macro_rules! ilias_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(transparent)
+        )]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(id: impl Into<String>) -> Self {
+                Self(id.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+    };
+}
+
+ilias_id!(
+    /// The id of a reference of an object in the repository tree (`ref_id`)
+    RefId
+);
+ilias_id!(
+    /// The id of an object itself (`obj_id`), shared by all of its references
+    ObjId
+);
+ilias_id!(
+    /// The id of an assignment of an exercise (`ass_id`)
+    AssignmentId
+);
+ilias_id!(
+    /// The id of a user or team as a member of an exercise (`member_id`)
+    MemberId
+);
+ilias_id!(
+    /// The id of a file delivered to an assignment (`delivered[]`)
+    DeliveredFileId
+);
+
+/// The id of a [`File`](crate::file::File), which depends on where the file is listed
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileId {
+    /// A file object in a folder
+    Ref(RefId),
+    /// A file delivered to an assignment
+    Delivered(DeliveredFileId),
+}
+
+impl FileId {
+    pub fn ref_id(&self) -> Option<&RefId> {
+        match self {
+            FileId::Ref(id) => Some(id),
+            FileId::Delivered(_) => None,
+        }
+    }
+
+    pub fn delivered_id(&self) -> Option<&DeliveredFileId> {
+        match self {
+            FileId::Delivered(id) => Some(id),
+            FileId::Ref(_) => None,
+        }
+    }
+}
+
+impl Display for FileId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileId::Ref(id) => write!(f, "{id}"),
+            FileId::Delivered(id) => write!(f, "{id}"),
+        }
+    }
+}
@@ -1,8 +1,51 @@
 use std::path::PathBuf;
+#[cfg(test)]
+use std::{
+    fs,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 /// A file on the local file system and the name it should have when uploaded
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NamedLocalFile {
     pub name: String,
     pub path: PathBuf,
 }
+
+/// Files in a temporary directory for tests, which is removed again when this is dropped
+#[cfg(test)]
+pub(crate) struct TestFiles {
+    directory: PathBuf,
+}
+
+#[cfg(test)]
+impl TestFiles {
+    pub(crate) fn new() -> TestFiles {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        // Tests run in parallel, so every instance needs its own directory
+        let directory = std::env::temp_dir().join(format!(
+            "ilias-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&directory).expect("Could not create test directory");
+        TestFiles { directory }
+    }
+
+    pub(crate) fn file(&self, name: &str, content: &[u8]) -> NamedLocalFile {
+        let path = self.directory.join(name);
+        fs::write(&path, content).expect("Could not write test file");
+        NamedLocalFile {
+            name: name.to_string(),
+            path,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestFiles {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use log::{debug, info};
+use regex::Regex;
+use reqwest::Url;
+use scraper::{ElementRef, Selector, selectable::Selectable};
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, Whatever};
+
+use super::{
+    IliasElement, client::IliasClient, folder::tree::sanitize_name, id::RefId, parse_date,
+    querypath::Querypath, reference::Reference, table::TableRow,
+};
+
+/// An Opencast series (the Opencast plugin object in ILIAS) with its episodes
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpencastSeries {
+    pub name: String,
+    pub episodes: Vec<OpencastEpisode>,
+}
+
+/// A single recording of an Opencast series. The available tracks are only fetched on demand
+/// via [`OpencastEpisode::get_tracks`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpencastEpisode {
+    pub title: String,
+    pub date: Option<DateTime<Local>>,
+    pub duration: Option<Duration>,
+    pub presenter: Option<String>,
+    tracks: Reference<Vec<OpencastTrack>>,
+}
+
+/// A downloadable video file of an episode
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpencastTrack {
+    /// The stream this track belongs to, e.g. "presenter" or "presentation"
+    pub content: String,
+    pub mimetype: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub url: String,
+}
+
+/// Which track to pick for each stream when downloading multiple episodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackQuality {
+    Highest,
+    Lowest,
+}
+
+static NAME_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TABLE_HEADER_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static EPISODE_ROW_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+impl IliasElement for OpencastSeries {
+    fn type_identifier() -> Option<&'static str> {
+        Some("xoct")
+    }
+
+    fn querypath_from_id(_id: &RefId) -> Option<String> {
+        None
+    }
+
+    fn parse(element: ElementRef, ilias_client: &IliasClient) -> Result<Self, Whatever> {
+        let name_selector = NAME_SELECTOR.get_or_init(|| {
+            Selector::parse(".il-page-content-header").expect("Could not parse selector")
+        });
+        let table_header_selector = TABLE_HEADER_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"table[id^="tbl_xoct_"] thead th"#).expect("Could not parse selector")
+        });
+        let episode_row_selector = EPISODE_ROW_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"table[id^="tbl_xoct_"] tbody tr"#).expect("Could not parse selector")
+        });
+
+        let name = element
+            .select(name_selector)
+            .next()
+            .whatever_context("Could not find name")?
+            .text()
+            .collect::<String>()
+            .trim()
+            .to_string();
+
+        let columns = TableRow::columns(element, table_header_selector);
+        debug!("Opencast columns: {columns:?}");
+
+        let mut episodes = vec![];
+        for row in element.select(episode_row_selector) {
+            if let Some(episode) = OpencastEpisode::parse(row, &columns, ilias_client.timezone())
+                .whatever_context("Could not parse opencast episode")?
+            {
+                episodes.push(episode);
+            }
+        }
+        debug!("Episodes: {episodes:?}");
+
+        Ok(OpencastSeries { name, episodes })
+    }
+}
+
+impl OpencastSeries {
+    /// Download one track per stream of every episode into `directory`, picking the track
+    /// resolution according to `quality`. Files are prefixed with the episode date, or the
+    /// position in the series if it has none. Returns the paths of the downloaded files.
+    pub fn download_all(
+        &mut self,
+        ilias_client: &IliasClient,
+        directory: &Path,
+        quality: TrackQuality,
+    ) -> Result<Vec<PathBuf>, Whatever> {
+        let mut downloaded = vec![];
+        for (index, episode) in self.episodes.iter_mut().enumerate() {
+            // Titles are not unique within a series, e.g. a lecture named after its course
+            let prefix = match episode.date {
+                Some(date) => date.format("%Y-%m-%d %H-%M").to_string(),
+                None => format!("{:03}", index + 1),
+            };
+            let error_context = format!("Could not get tracks of {}", episode.title);
+            let tracks = episode
+                .get_tracks(ilias_client)
+                .whatever_context(error_context)?
+                .to_vec();
+            let selected_tracks = OpencastTrack::select(&tracks, quality);
+            let multiple_streams = selected_tracks.len() > 1;
+
+            for track in selected_tracks {
+                let file_name = if multiple_streams {
+                    format!(
+                        "{prefix} {} ({}).{}",
+                        episode.title,
+                        track.content,
+                        track.extension()
+                    )
+                } else {
+                    format!("{prefix} {}.{}", episode.title, track.extension())
+                };
+                let path = directory.join(sanitize_name(&file_name));
+
+                episode
+                    .download_track(ilias_client, track, &path)
+                    .whatever_context(format!("Could not download {}", episode.title))?;
+                downloaded.push(path);
+            }
+        }
+
+        Ok(downloaded)
+    }
+}
+
+static PLAY_LINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+static STREAMS_REGEX: OnceLock<Regex> = OnceLock::new();
+
+impl OpencastEpisode {
+    /// The tracks as far as they were fetched, see [`OpencastEpisode::get_tracks`]
+    pub fn tracks(&self) -> &Reference<Vec<OpencastTrack>> {
+        &self.tracks
+    }
+
+    /// Parse a row of the episode table. Returns `None` for rows without a playable recording
+    /// (e.g. scheduled or still processing events).
+    fn parse(
+        row: ElementRef,
+        columns: &[String],
+        timezone: Tz,
+    ) -> Result<Option<OpencastEpisode>, Whatever> {
+        let play_link_selector = PLAY_LINK_SELECTOR.get_or_init(|| {
+            Selector::parse(r#"a[href*="cmd=streamVideo"]"#).expect("Could not parse selector")
+        });
+
+        let Some(play_link) = row.select(play_link_selector).next() else {
+            debug!("Skipping opencast row without play link");
+            return Ok(None);
+        };
+        let player_querypath = play_link
+            .attr("href")
+            .whatever_context("Play link had no href")?
+            .to_string();
+
+        let table_row = TableRow::parse(row, columns);
+
+        let title = table_row
+            .value(&["Titel", "Title"])
+            .whatever_context("Could not find title of opencast episode")?
+            .to_string();
+        let presenter = table_row
+            .value(&["Vortragende", "Presenter", "Presenters"])
+            .cloned();
+        let date = table_row
+            .value(&["Datum", "Date", "Startzeit", "Start"])
+            // Opencast renders dates like 12.11.2024 - 10:00
+            .and_then(|date| parse_date(date, timezone).ok());
+        let duration = table_row
+            .value(&["Dauer", "Duration"])
+            .and_then(|duration| Self::parse_duration(duration).ok());
+
+        Ok(Some(OpencastEpisode {
+            title,
+            date,
+            duration,
+            presenter,
+            tracks: Reference::Unresolved(Querypath::from(player_querypath)),
+        }))
+    }
+
+    fn parse_duration(duration_string: &str) -> Result<Duration, Whatever> {
+        let mut seconds = 0;
+        for part in duration_string.split(':') {
+            let part: u64 = part
+                .trim()
+                .parse()
+                .whatever_context(format!("Unable to parse duration: {duration_string}"))?;
+            seconds = seconds * 60 + part;
+        }
+        Ok(Duration::from_secs(seconds))
+    }
+
+    pub fn get_tracks(&mut self, ilias_client: &IliasClient) -> Result<&[OpencastTrack], Whatever> {
+        let streams_regex = STREAMS_REGEX.get_or_init(|| {
+            Regex::new(r#"(?<streams>\{"streams"[\s\S]+?),\s*\{"paella_config_file"#)
+                .expect("Could not parse regex")
+        });
+
+        let tracks = &mut self.tracks;
+        if let Reference::Unresolved(querypath) = tracks {
+            let player_page = ilias_client
+                .get_querypath(querypath.as_str())
+                .whatever_context("Could not get opencast player page")?;
+            let player_page = player_page.html();
+            let streams_json = &streams_regex
+                .captures(&player_page)
+                .whatever_context("Did not find stream data on player page")?["streams"];
+
+            let player_data: PaellaData = serde_json::from_str(streams_json)
+                .whatever_context("Could not deserialize stream data")?;
+            let parsed_tracks = player_data
+                .streams
+                .into_iter()
+                .flat_map(|stream| {
+                    let content = stream.content;
+                    stream
+                        .sources
+                        .into_iter()
+                        .filter(|(format, _)| format == "mp4")
+                        .flat_map(|(_, sources)| sources)
+                        .map(move |source| OpencastTrack {
+                            content: content.clone(),
+                            mimetype: source.mimetype,
+                            width: source.res.as_ref().map(|res| res.w),
+                            height: source.res.as_ref().map(|res| res.h),
+                            url: source.src,
+                        })
+                })
+                .collect();
+            *tracks = Reference::Resolved(parsed_tracks);
+        }
+
+        Ok(tracks
+            .try_get_resolved()
+            .whatever_context("Tracks of episode are unavailable")?)
+    }
+
+    pub fn download_track(
+        &self,
+        ilias_client: &IliasClient,
+        track: &OpencastTrack,
+        to: &Path,
+    ) -> Result<(), Whatever> {
+        let url = Url::parse(&track.url).whatever_context("Track had invalid url")?;
+        info!("Downloading {} ({}) to {to:?}", self.title, track);
+        ilias_client.download_url(url, to)
+    }
+}
+
+impl Display for OpencastEpisode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.title)
+    }
+}
+
+impl OpencastTrack {
+    /// Pick one track per stream content according to `quality`
+    pub fn select(tracks: &[OpencastTrack], quality: TrackQuality) -> Vec<&OpencastTrack> {
+        let mut selected: Vec<&OpencastTrack> = vec![];
+        for track in tracks {
+            match selected
+                .iter_mut()
+                .find(|selected| selected.content == track.content)
+            {
+                None => selected.push(track),
+                Some(selected) => {
+                    let is_better = match quality {
+                        TrackQuality::Highest => track.pixels() > selected.pixels(),
+                        TrackQuality::Lowest => track.pixels() < selected.pixels(),
+                    };
+                    if is_better {
+                        *selected = track;
+                    }
+                }
+            }
+        }
+        selected
+    }
+
+    fn pixels(&self) -> u32 {
+        self.width.unwrap_or(0) * self.height.unwrap_or(0)
+    }
+
+    fn extension(&self) -> &str {
+        self.mimetype
+            .as_deref()
+            .and_then(|mimetype| mimetype.split('/').nth(1))
+            .unwrap_or("mp4")
+    }
+}
+
+impl Display for OpencastTrack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => write!(f, "{} {width}x{height}", self.content),
+            _ => write!(f, "{}", self.content),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PaellaData {
+    streams: Vec<PaellaStream>,
+}
+
+#[derive(Deserialize)]
+struct PaellaStream {
+    #[serde(default)]
+    content: String,
+    sources: HashMap<String, Vec<PaellaSource>>,
+}
+
+#[derive(Deserialize)]
+struct PaellaSource {
+    src: String,
+    mimetype: Option<String>,
+    res: Option<PaellaResolution>,
+}
+
+#[derive(Deserialize)]
+struct PaellaResolution {
+    w: u32,
+    h: u32,
+}
@@ -0,0 +1,111 @@
+use std::fmt::Display;
+
+use log::warn;
+use scraper::{ElementRef, Selector, selector::ToCss};
+use snafu::{ErrorCompat, ResultExt, Whatever};
+
+/// How many characters of the html of a skipped element are kept in its warning
+const EXCERPT_LENGTH: usize = 300;
+
+/// Whether parsing aborts on the first element that can not be parsed or skips it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    #[default]
+    Strict,
+    /// Skip elements that can not be parsed and report them as [`ParseWarning`]s
+    Lenient,
+}
+
+/// An element that was skipped during lenient parsing
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseWarning {
+    /// The beginning of the html of the skipped element
+    pub excerpt: String,
+    /// The selector the skipped element was selected with
+    pub selector: String,
+    pub reason: String,
+}
+
+/// A parsed value together with the warnings for the elements that were skipped
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parsed<T> {
+    pub value: T,
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl<T> Parsed<T> {
+    pub fn new(value: T) -> Parsed<T> {
+        Parsed {
+            value,
+            warnings: vec![],
+        }
+    }
+
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Skipped {} ({}): {}",
+            self.selector, self.reason, self.excerpt
+        )
+    }
+}
+
+/// Collects the warnings of a lenient parse, or passes errors on when parsing strictly
+pub(crate) struct Warnings {
+    mode: ParseMode,
+    warnings: Vec<ParseWarning>,
+}
+
+impl Warnings {
+    pub(crate) fn new(mode: ParseMode) -> Warnings {
+        Warnings {
+            mode,
+            warnings: vec![],
+        }
+    }
+
+    /// The parsed value of `element`. On failure, strict parsing fails with `context` and lenient
+    /// parsing records a warning and returns `None`.
+    pub(crate) fn check<T>(
+        &mut self,
+        result: Result<T, Whatever>,
+        element: ElementRef,
+        selector: &Selector,
+        context: &str,
+    ) -> Result<Option<T>, Whatever> {
+        match (result, self.mode) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(err), ParseMode::Strict) => Err(err).whatever_context(context.to_string()),
+            (Err(err), ParseMode::Lenient) => {
+                let reason = err
+                    .iter_chain()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(": ");
+                let warning = ParseWarning {
+                    excerpt: element.html().chars().take(EXCERPT_LENGTH).collect(),
+                    selector: selector.to_css_string(),
+                    reason: format!("{context}: {reason}"),
+                };
+                warn!("{warning}");
+                self.warnings.push(warning);
+                Ok(None)
+            }
+        }
+    }
+
+    pub(crate) fn finish<T>(self, value: T) -> Parsed<T> {
+        Parsed {
+            value,
+            warnings: self.warnings,
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::{fmt::Display, ops::Deref, str::FromStr, sync::OnceLock};
+
+use regex::Regex;
+use reqwest::Url;
+
+use super::{Querypath as _, id::RefId};
+
+/// A parsed querypath, the part of an ILIAS url after the host
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "String", into = "String")
+)]
+pub struct Querypath {
+    raw: String,
+    path: String,
+    params: Vec<(String, String)>,
+}
+
+static REF_ID_REGEX: OnceLock<Regex> = OnceLock::new();
+
+impl Querypath {
+    /// Parse a querypath. Absolute urls are accepted as well, their scheme and host are dropped.
+    pub fn parse(querypath: &str) -> Querypath {
+        let (raw, url) = match Url::parse(querypath) {
+            Ok(url) if url.has_host() => {
+                let raw = match url.query() {
+                    Some(query) => format!("{}?{query}", url.path()),
+                    None => url.path().to_string(),
+                };
+                (raw.trim_start_matches('/').to_string(), url)
+            }
+            _ => {
+                // Only used to split and decode the querypath
+                let mut url = Url::parse("https://localhost").expect("Could not parse dummy url");
+                url.set_querypath(querypath);
+                (querypath.to_string(), url)
+            }
+        };
+
+        Querypath {
+            raw,
+            path: url.path().trim_start_matches('/').to_string(),
+            params: url.query_pairs().into_owned().collect(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The path without query, e.g. `ilias.php` or `goto.php/fold/123`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// The (decoded) value of the query parameter `name`
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param_name, _)| param_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn base_class(&self) -> Option<&str> {
+        self.param("baseClass")
+    }
+
+    pub fn cmd(&self) -> Option<&str> {
+        self.param("cmd")
+    }
+
+    pub fn cmd_class(&self) -> Option<&str> {
+        self.param("cmdClass")
+    }
+
+    /// The ref id from the `ref_id` parameter or a permanent link
+    pub fn ref_id(&self) -> Option<RefId> {
+        let ref_id_regex = REF_ID_REGEX.get_or_init(|| {
+            Regex::new(r"(ref_id=|target=[a-z]+_|goto\.php/[a-z]+/|exc/)(?<id>\d+)")
+                .expect("Could not parse regex")
+        });
+        ref_id_regex
+            .captures(&self.raw)
+            .map(|captures| RefId::new(&captures["id"]))
+    }
+
+    /// A copy of this querypath with the parameter `name` set to `value`
+    pub fn with_param(&self, name: &str, value: &str) -> Querypath {
+        let mut url = Url::parse("https://localhost").expect("Could not parse dummy url");
+        url.set_path(&self.path);
+        {
+            let mut query = url.query_pairs_mut();
+            let mut replaced = false;
+            for (param_name, param_value) in &self.params {
+                if param_name == name {
+                    if !replaced {
+                        query.append_pair(name, value);
+                        replaced = true;
+                    }
+                } else {
+                    query.append_pair(param_name, param_value);
+                }
+            }
+            if !replaced {
+                query.append_pair(name, value);
+            }
+        }
+        Querypath::parse(url.get_querypath().trim_start_matches('/'))
+    }
+}
+
+impl super::Querypath for Querypath {
+    fn get_querypath(&self) -> String {
+        self.raw.clone()
+    }
+
+    fn set_querypath(&mut self, querypath: &str) {
+        *self = Querypath::parse(querypath);
+    }
+}
+
+impl Display for Querypath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Deref for Querypath {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl AsRef<str> for Querypath {
+    fn as_ref(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl FromStr for Querypath {
+    type Err = std::convert::Infallible;
+
+    fn from_str(querypath: &str) -> Result<Self, Self::Err> {
+        Ok(Querypath::parse(querypath))
+    }
+}
+
+impl From<&str> for Querypath {
+    fn from(querypath: &str) -> Self {
+        Querypath::parse(querypath)
+    }
+}
+
+impl From<String> for Querypath {
+    fn from(querypath: String) -> Self {
+        Querypath::parse(&querypath)
+    }
+}
+
+impl From<Querypath> for String {
+    fn from(querypath: Querypath) -> Self {
+        querypath.raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relative_querypath() {
+        let querypath = Querypath::parse(
+            "ilias.php?baseClass=ilexercisehandlergui&ref_id=123&cmd=showOverview&cmdClass=ilobjexercisegui",
+        );
+        assert_eq!(querypath.path(), "ilias.php");
+        assert_eq!(querypath.base_class(), Some("ilexercisehandlergui"));
+        assert_eq!(querypath.cmd(), Some("showOverview"));
+        assert_eq!(querypath.cmd_class(), Some("ilobjexercisegui"));
+        assert_eq!(querypath.ref_id(), Some(RefId::new("123")));
+        assert_eq!(querypath.param("missing"), None);
+    }
+
+    #[test]
+    fn parses_absolute_url() {
+        let querypath = Querypath::parse("https://ilias.example.com/goto.php?target=fold_42");
+        assert_eq!(querypath.as_str(), "goto.php?target=fold_42");
+        assert_eq!(querypath.path(), "goto.php");
+        assert_eq!(querypath.ref_id(), Some(RefId::new("42")));
+    }
+
+    #[test]
+    fn extracts_ref_id_from_permalinks() {
+        assert_eq!(
+            Querypath::parse("goto.php/fold/7").ref_id(),
+            Some(RefId::new("7"))
+        );
+        assert_eq!(
+            Querypath::parse("/go/exc/8/").ref_id(),
+            Some(RefId::new("8"))
+        );
+        assert_eq!(Querypath::parse("ilias.php?cmd=view").ref_id(), None);
+    }
+
+    #[test]
+    fn with_param_replaces_existing_value() {
+        let querypath = Querypath::parse("ilias.php?ref_id=1&cmd=view&cmd=other");
+        let changed = querypath.with_param("cmd", "edit");
+        assert_eq!(changed.as_str(), "ilias.php?ref_id=1&cmd=edit");
+    }
+
+    #[test]
+    fn with_param_appends_new_value() {
+        let querypath = Querypath::parse("ilias.php?ref_id=1");
+        assert_eq!(
+            querypath.with_param("name", "a b&c").as_str(),
+            "ilias.php?ref_id=1&name=a+b%26c"
+        );
+    }
+
+    #[test]
+    fn with_param_keeps_absolute_url_relative() {
+        let querypath = Querypath::parse("https://ilias.example.com/ilias.php?ref_id=1");
+        assert_eq!(
+            querypath.with_param("cmd", "view").as_str(),
+            "ilias.php?ref_id=1&cmd=view"
+        );
+    }
+}
@@ -1,16 +1,18 @@
+use scraper::Html;
 use snafu::{ResultExt, Whatever, whatever};
 
-use crate::{IliasElement, client::IliasClient};
+use crate::{IliasElement, client::IliasClient, parsed::Parsed, querypath::Querypath};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Reference<T> {
     Unavailable,
-    Unresolved(String),
+    Unresolved(Querypath),
     Resolved(T),
 }
 
 impl<T> Reference<T> {
-    pub fn from_optional_querypath(querypath: Option<String>) -> Reference<T> {
+    pub fn from_optional_querypath(querypath: Option<Querypath>) -> Reference<T> {
         match querypath {
             None => Self::Unavailable,
             Some(querypath) => Self::Unresolved(querypath),
@@ -27,15 +29,25 @@ impl<T> Reference<T> {
 
 impl<T: IliasElement> Reference<T> {
     pub fn resolve(&self, ilias_client: &IliasClient) -> Result<T, Whatever> {
+        let element = self.get_page(ilias_client)?;
+        T::parse(element.root_element(), ilias_client)
+    }
+
+    /// Like [`Reference::resolve`], but parses leniently, see [`IliasElement::parse_lenient`]
+    pub fn resolve_lenient(&self, ilias_client: &IliasClient) -> Result<Parsed<T>, Whatever> {
+        let element = self.get_page(ilias_client)?;
+        T::parse_lenient(element.root_element(), ilias_client)
+    }
+
+    fn get_page(&self, ilias_client: &IliasClient) -> Result<Html, Whatever> {
         let querypath = match self {
             Self::Unavailable => whatever!("Reference unavailable"),
             Self::Resolved(_) => whatever!("Already resolved"),
             Self::Unresolved(querypath) => querypath,
         };
 
-        let element = ilias_client
-            .get_querypath(querypath)
-            .whatever_context("Could not get querypath from element")?;
-        T::parse(element.root_element(), ilias_client)
+        ilias_client
+            .get_querypath(querypath.as_str())
+            .whatever_context("Could not get querypath from element")
     }
 }
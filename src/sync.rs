@@ -0,0 +1,310 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Local};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt, Whatever, whatever};
+
+use super::{
+    client::IliasClient,
+    file::File,
+    folder::{Folder, tree::WalkOptions},
+};
+
+/// Name of the manifest file that is stored in the root of a synced directory
+pub const MANIFEST_FILE_NAME: &str = ".ilias-sync.json";
+
+/// What to do with a local file that was edited locally while the remote file changed too
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Leave the local file alone and report the conflict
+    #[default]
+    KeepLocal,
+    /// Replace the local file with the remote one
+    Overwrite,
+    /// Rename the local file to `<name> (local).<ext>` (or `(local 2)` and so on if that exists)
+    /// and download the remote one
+    KeepBoth,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SyncOptions {
+    pub walk: WalkOptions,
+    pub conflict_policy: ConflictPolicy,
+    /// Delete local files whose remote counterpart is gone. Locally edited files are never
+    /// deleted.
+    pub delete_removed: bool,
+}
+
+/// The changes made by [`Folder::sync_to`], as paths relative to the synced directory
+#[derive(Clone, Debug, Default)]
+pub struct SyncReport {
+    pub added: Vec<PathBuf>,
+    pub updated: Vec<PathBuf>,
+    /// Local files that were deleted because they are gone remotely
+    pub removed: Vec<PathBuf>,
+    /// Local files that are gone remotely, but were kept because they were modified locally or
+    /// [`SyncOptions::delete_removed`] is not set
+    pub kept: Vec<PathBuf>,
+    pub conflicts: Vec<PathBuf>,
+    /// Remote files sharing their name with another file in the same folder. They are synced with
+    /// their id appended to the name.
+    pub duplicates: Vec<PathBuf>,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncManifest {
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+/// State of a file at the time it was last downloaded
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    id: Option<String>,
+    remote_date: Option<DateTime<Local>>,
+    local_size: u64,
+    local_modified: SystemTime,
+}
+
+impl Folder {
+    /// Mirror all files of this folder and its sub-containers into `directory`. Only files that
+    /// are new or changed since the last sync are downloaded, which is tracked in a manifest file
+    /// inside of `directory`.
+    pub fn sync_to(
+        &self,
+        ilias_client: &IliasClient,
+        directory: &Path,
+        options: &SyncOptions,
+    ) -> Result<SyncReport, Whatever> {
+        fs::create_dir_all(directory)
+            .whatever_context(format!("Could not create {directory:?}"))?;
+        let mut manifest = SyncManifest::load(directory)?;
+        let tree = self
+            .walk(ilias_client, &options.walk)
+            .whatever_context("Could not resolve folder tree")?;
+
+        let entries = tree.entries();
+        let mut name_counts: HashMap<&[String], usize> = HashMap::new();
+        for entry in &entries {
+            if entry.element.file().is_some() {
+                *name_counts.entry(&entry.path).or_default() += 1;
+            }
+        }
+
+        let mut report = SyncReport::default();
+        let mut seen = HashSet::new();
+        for entry in &entries {
+            let Some(file) = entry.element.file() else {
+                continue;
+            };
+            // The first component is the name of the synced folder itself
+            let mut path = entry.path[1..].to_vec();
+            if name_counts[entry.path.as_slice()] > 1 {
+                let Some(id) = &file.id else {
+                    whatever!("Can not tell apart the files at {}", entry.path_string())
+                };
+                report.duplicates.push(path.iter().collect());
+                let name = path
+                    .last_mut()
+                    .whatever_context("Empty path in folder tree")?;
+                *name = name_with_suffix(name, &format!(" ({id})"));
+            }
+            let key = path.join("/");
+            let relative_path = key_to_path(&key)
+                .whatever_context(format!("{key} would be synced outside of {directory:?}"))?;
+            let local_path = directory.join(&relative_path);
+            seen.insert(key.clone());
+
+            let is_conflict = match manifest.files.get(&key) {
+                None => local_path.exists(),
+                Some(manifest_entry) => {
+                    let remote_changed = manifest_entry.id
+                        != file.id.as_ref().map(ToString::to_string)
+                        || manifest_entry.remote_date != file.date;
+                    if !remote_changed && local_path.exists() {
+                        report.unchanged += 1;
+                        continue;
+                    }
+                    remote_changed && manifest_entry.is_modified(&local_path)
+                }
+            };
+
+            if is_conflict {
+                match options.conflict_policy {
+                    ConflictPolicy::KeepLocal => {
+                        info!("Not touching locally modified {key}");
+                        report.conflicts.push(relative_path);
+                        continue;
+                    }
+                    ConflictPolicy::Overwrite => {
+                        info!("Overwriting locally modified {key}");
+                    }
+                    ConflictPolicy::KeepBoth => {
+                        let backup_path = backup_path(&local_path);
+                        info!("Moving locally modified {key} to {backup_path:?}");
+                        fs::rename(&local_path, &backup_path)
+                            .whatever_context(format!("Could not move {local_path:?}"))?;
+                    }
+                }
+                report.conflicts.push(relative_path.clone());
+            }
+
+            let was_known = manifest.files.contains_key(&key);
+            let manifest_entry = download(ilias_client, file, &local_path)
+                .whatever_context(format!("Could not download {key}"))?;
+            manifest.files.insert(key, manifest_entry);
+            // Saving after every download keeps the manifest accurate if a later download fails
+            manifest.save(directory)?;
+            if was_known {
+                report.updated.push(relative_path);
+            } else {
+                report.added.push(relative_path);
+            }
+        }
+
+        let removed_keys: Vec<String> = manifest
+            .files
+            .keys()
+            .filter(|key| !seen.contains(*key))
+            .cloned()
+            .collect();
+        for key in removed_keys {
+            let manifest_entry = manifest.files.remove(&key).unwrap();
+            let Some(relative_path) = key_to_path(&key) else {
+                debug!("Ignoring manifest entry {key} outside of {directory:?}");
+                continue;
+            };
+            let local_path = directory.join(&relative_path);
+            if !local_path.exists() {
+                continue;
+            }
+
+            if options.delete_removed && !manifest_entry.is_modified(&local_path) {
+                fs::remove_file(&local_path)
+                    .whatever_context(format!("Could not delete {local_path:?}"))?;
+                report.removed.push(relative_path);
+            } else {
+                info!("Keeping {key} that was removed remotely");
+                report.kept.push(relative_path);
+            }
+        }
+
+        manifest.save(directory)?;
+        debug!("Sync report: {report:?}");
+        Ok(report)
+    }
+}
+
+impl SyncManifest {
+    fn load(directory: &Path) -> Result<SyncManifest, Whatever> {
+        let path = directory.join(MANIFEST_FILE_NAME);
+        if !path.exists() {
+            return Ok(SyncManifest::default());
+        }
+
+        let content =
+            fs::read_to_string(&path).whatever_context(format!("Could not read {path:?}"))?;
+        serde_json::from_str(&content).whatever_context(format!("Could not parse {path:?}"))
+    }
+
+    fn save(&self, directory: &Path) -> Result<(), Whatever> {
+        let path = directory.join(MANIFEST_FILE_NAME);
+        let content =
+            serde_json::to_string_pretty(self).whatever_context("Could not serialize manifest")?;
+        fs::write(&path, content).whatever_context(format!("Could not write {path:?}"))
+    }
+}
+
+impl ManifestEntry {
+    fn from_local_file(file: &File, local_path: &Path) -> Result<ManifestEntry, Whatever> {
+        let metadata = fs::metadata(local_path)
+            .whatever_context(format!("Could not get metadata of {local_path:?}"))?;
+        Ok(ManifestEntry {
+            id: file.id.as_ref().map(ToString::to_string),
+            remote_date: file.date,
+            local_size: metadata.len(),
+            local_modified: metadata
+                .modified()
+                .whatever_context("Could not get modification time")?,
+        })
+    }
+
+    /// Whether the local file differs from the state it was downloaded in
+    fn is_modified(&self, local_path: &Path) -> bool {
+        match fs::metadata(local_path) {
+            Ok(metadata) => {
+                metadata.len() != self.local_size
+                    || metadata
+                        .modified()
+                        .is_ok_and(|modified| modified != self.local_modified)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Download to a temporary file first, so an interrupted download never replaces a local file
+fn download(
+    ilias_client: &IliasClient,
+    file: &File,
+    local_path: &Path,
+) -> Result<ManifestEntry, Whatever> {
+    let parent = local_path
+        .parent()
+        .whatever_context("Local path has no parent")?;
+    fs::create_dir_all(parent).whatever_context(format!("Could not create {parent:?}"))?;
+
+    let mut partial_path = local_path.as_os_str().to_owned();
+    partial_path.push(".part");
+    let partial_path = PathBuf::from(partial_path);
+
+    file.download(ilias_client, &partial_path)?;
+    fs::rename(&partial_path, local_path)
+        .whatever_context(format!("Could not move download to {local_path:?}"))?;
+    info!("Downloaded {local_path:?}");
+
+    ManifestEntry::from_local_file(file, local_path)
+}
+
+/// The relative local path for a `/` separated manifest key, `None` if it would leave the synced
+/// directory
+fn key_to_path(key: &str) -> Option<PathBuf> {
+    let path: PathBuf = key.split('/').collect();
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then_some(path)
+}
+
+/// A free path next to `local_path` to move a locally modified file to
+fn backup_path(local_path: &Path) -> PathBuf {
+    let name = local_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut counter = 1;
+    loop {
+        let suffix = match counter {
+            1 => " (local)".to_string(),
+            _ => format!(" (local {counter})"),
+        };
+        let path = local_path.with_file_name(name_with_suffix(&name, &suffix));
+        if !path.exists() {
+            return path;
+        }
+        counter += 1;
+    }
+}
+
+/// Insert `suffix` into a file name in front of its extension
+fn name_with_suffix(name: &str, suffix: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{stem}{suffix}.{extension}"),
+        _ => format!("{name}{suffix}"),
+    }
+}
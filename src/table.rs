@@ -0,0 +1,120 @@
+use std::sync::OnceLock;
+
+use log::debug;
+use scraper::{ElementRef, Html, Selector};
+use snafu::{ResultExt, Whatever};
+
+use super::{client::IliasClient, querypath::Querypath};
+
+static TABLE_CONTROL_LINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static CELL_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+/// A row of a table whose cells can be looked up by the name of their column
+#[derive(Debug)]
+pub struct TableRow<'a> {
+    columns: &'a [String],
+    cells: Vec<String>,
+}
+
+impl<'a> TableRow<'a> {
+    /// The trimmed texts of the header cells matched by `header_selector`
+    pub fn columns(page: ElementRef, header_selector: &Selector) -> Vec<String> {
+        page.select(header_selector)
+            .map(|header| header.text().collect::<String>().trim().to_string())
+            .collect()
+    }
+
+    pub fn parse(row: ElementRef, columns: &'a [String]) -> TableRow<'a> {
+        let cell_selector =
+            CELL_SELECTOR.get_or_init(|| Selector::parse("td").expect("Could not parse selector"));
+
+        let cells = row
+            .select(cell_selector)
+            .map(|cell| cell.text().collect::<String>().trim().to_string())
+            .collect();
+        TableRow { columns, cells }
+    }
+
+    /// The value in the first column named like one of `names`, if it is not empty. ILIAS
+    /// column names depend on the language, so callers pass all known variants.
+    pub fn value(&self, names: &[&str]) -> Option<&String> {
+        self.columns
+            .iter()
+            .position(|column| names.contains(&column.as_str()))
+            .and_then(|index| self.cells.get(index))
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// All pages of the table `table_id` on `page`, with the row limit raised as far as ILIAS allows.
+/// `None` if `page` already shows all rows.
+pub fn all_pages(
+    ilias_client: &IliasClient,
+    page: ElementRef,
+    table_id: &str,
+) -> Result<Option<Vec<Html>>, Whatever> {
+    let nav_param = format!("{table_id}_table_nav");
+    let rows_param = format!("{table_id}_trows");
+
+    let links = control_links(page);
+    let Some((nav_link, nav_value)) = links.iter().find_map(|link| {
+        link.param(&nav_param)
+            .and_then(|value| value.rsplit_once(':'))
+            .map(|(ordering, _)| (link, ordering))
+    }) else {
+        return Ok(None);
+    };
+    let max_rows = links
+        .iter()
+        .filter_map(|link| link.param(&rows_param)?.parse::<u32>().ok())
+        .max();
+
+    let mut querypath = nav_link.with_param(&nav_param, &format!("{nav_value}:0"));
+    if let Some(max_rows) = max_rows {
+        querypath = querypath.with_param(&rows_param, &max_rows.to_string());
+    }
+
+    let mut pages = vec![];
+    let mut offset = 0;
+    loop {
+        debug!("Getting page of table {table_id} at offset {offset}");
+        let page = ilias_client
+            .get_querypath(querypath.as_str())
+            .whatever_context(format!("Could not get page of table {table_id}"))?;
+        let next_page = next_page(page.root_element(), &nav_param, offset);
+        pages.push(page);
+
+        match next_page {
+            Some((next_querypath, next_offset)) => {
+                querypath = next_querypath;
+                offset = next_offset;
+            }
+            None => break,
+        }
+    }
+    Ok(Some(pages))
+}
+
+fn control_links(page: ElementRef) -> Vec<Querypath> {
+    let table_control_link_selector = TABLE_CONTROL_LINK_SELECTOR.get_or_init(|| {
+        Selector::parse(r#"a[href*="_table_nav="], a[href*="_trows="]"#)
+            .expect("Could not parse selector")
+    });
+
+    page.select(table_control_link_selector)
+        .filter_map(|link| link.attr("href"))
+        .map(Querypath::parse)
+        .collect()
+}
+
+/// The navigation link with the smallest offset after `offset`
+fn next_page(page: ElementRef, nav_param: &str, offset: u32) -> Option<(Querypath, u32)> {
+    control_links(page)
+        .into_iter()
+        .filter_map(|link| {
+            let link_offset = link.param(nav_param)?.rsplit(':').next()?.parse().ok()?;
+            Some((link, link_offset))
+        })
+        .filter(|&(_, link_offset)| link_offset > offset)
+        .min_by_key(|&(_, link_offset)| link_offset)
+}